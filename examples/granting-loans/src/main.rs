@@ -1,13 +1,11 @@
-use std::collections::HashMap;
 use std::time::SystemTime;
 
 use chrono::{DateTime, Utc};
-use log::{error, info, LevelFilter};
-use serde_json::Value;
+use log::{info, LevelFilter};
 
 use camunda_external_task_client_rust::*;
 
-fn request_rejecter_handler(task: &Task, engine_service: &EngineService) {
+fn request_rejecter_handler(task: &Task, _engine_service: &EngineService) -> HandlerResult {
     if let Some(bar) = task.variables.get("bar") {
         info!("bar: {bar}");
     }
@@ -15,40 +13,35 @@ fn request_rejecter_handler(task: &Task, engine_service: &EngineService) {
         info!("creditScores: {credit_scores}");
     }
 
-    let res_complete = engine_service.complete(task, None, None);
-    match res_complete {
-        Ok(_) => info!("Rejection processed"),
-        Err(e) => error!("Failed to process rejection, {e:?}"),
-    };
+    info!("Rejection processed");
+    Ok(TaskOutcome::complete())
 }
 
-fn credit_score_checker_handler(task: &Task, engine_service: &EngineService) {
-    let default_score = task
-        .variables
-        .get("defaultScore")
-        .unwrap()
-        .value
-        .as_u64()
-        .unwrap();
-    info!("defaultScore: {default_score}");
+#[derive(ProcessVariables)]
+struct ScoreRequest {
+    #[variable(name = "defaultScore")]
+    default_score: i64,
+}
 
-    let credit_scores = vec![default_score, 9, 1, 4, 10];
-    let mut process_variables: HashMap<String, Variable> = HashMap::new();
-    process_variables.insert(
-        "creditScores".into(),
-        Variable::new("json", Value::String(format!("{credit_scores:?}"))),
-    );
-    let date_time: DateTime<Utc> = SystemTime::now().into();
-    process_variables.insert(
-        "bar".into(),
-        Variable::new("string", Value::String(date_time.to_rfc3339())),
-    );
+#[derive(ProcessVariables)]
+struct ScoreResult {
+    #[variable(name = "creditScores", type = "json")]
+    credit_scores: Vec<i64>,
+    bar: String,
+}
 
-    let res_complete = engine_service.complete(task, Some(process_variables), None);
-    match res_complete {
-        Ok(_) => info!("I completed my task successfully!!"),
-        Err(e) => error!("Failed completing my task, {e:?}"),
+fn credit_score_checker_handler(task: &Task, _engine_service: &EngineService) -> HandlerResult {
+    let request = ScoreRequest::from_variables(&task.variables)?;
+    info!("defaultScore: {}", request.default_score);
+
+    let date_time: DateTime<Utc> = SystemTime::now().into();
+    let result = ScoreResult {
+        credit_scores: vec![request.default_score, 9, 1, 4, 10],
+        bar: date_time.to_rfc3339(),
     };
+
+    info!("I completed my task successfully!!");
+    Ok(TaskOutcome::complete_from(&result)?)
 }
 
 fn main() -> Result<()> {
@@ -63,6 +56,7 @@ fn main() -> Result<()> {
     // subscribe to topic
     client
         .subscribe("creditScoreChecker")
+        .variables_from::<ScoreRequest>()
         .handler(credit_score_checker_handler)
         .open()?;
 
@@ -3,26 +3,25 @@ use std::time::SystemTime;
 
 use chrono::{DateTime, Local, Timelike, Utc};
 use log::{info, LevelFilter};
-use serde_json::Value;
 
 use camunda_external_task_client_rust::*;
 
-fn invoice_creator_handler(task: &Task, engine_service: &EngineService) {
+#[external_task(topic = "invoiceCreator", lock_duration = 60000)]
+fn invoice_creator_handler(_task: &Task, _engine_service: &EngineService) -> HandlerResult {
     let mut variables: HashMap<String, Variable> = HashMap::new();
     let date_time: DateTime<Utc> = SystemTime::now().into();
-    variables.insert(
-        "date".into(),
-        Variable::new("string", Value::String(date_time.to_rfc3339())),
-    );
+    variables.insert("date".into(), Variable::string(date_time.to_rfc3339()));
     // TODO read invoice.txt
 
     let minute = Local::now().minute();
-    let _ = if minute % 2 == 0 {
-        engine_service.complete(task, Some(variables), None)
+    if minute.is_multiple_of(2) {
+        Ok(TaskOutcome::complete_with(variables))
     } else {
-        engine_service.complete(task, None, Some(variables))
+        Ok(TaskOutcome::Complete {
+            variables: None,
+            local_variables: Some(variables),
+        })
     }
-    .unwrap();
 }
 
 fn main() -> Result<()> {
@@ -34,11 +33,8 @@ fn main() -> Result<()> {
         .with_max_tasks(100);
     let mut client = Client::new(config)?;
 
-    // subscribe to topic
-    client
-        .subscribe("invoiceCreator")
-        .handler(invoice_creator_handler)
-        .open()?;
+    // subscribe to the topics of all #[external_task] handlers
+    client.register_all()?;
 
     // wait for client thread
     client.join()?;
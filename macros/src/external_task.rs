@@ -0,0 +1,69 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::{Expr, ExprArray, ItemFn, Lit, LitInt, LitStr};
+
+#[derive(Default)]
+pub(crate) struct ExternalTaskArgs {
+    topic: Option<LitStr>,
+    lock_duration: Option<LitInt>,
+    variables: Option<Vec<LitStr>>,
+}
+
+impl ExternalTaskArgs {
+    pub(crate) fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("topic") {
+            self.topic = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("lock_duration") {
+            self.lock_duration = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("variables") {
+            let array: ExprArray = meta.value()?.parse()?;
+            let variables = array
+                .elems
+                .iter()
+                .map(|elem| match elem {
+                    Expr::Lit(expr) => match &expr.lit {
+                        Lit::Str(lit) => Ok(lit.clone()),
+                        lit => Err(syn::Error::new_spanned(lit, "expected a variable name")),
+                    },
+                    elem => Err(syn::Error::new_spanned(elem, "expected a variable name")),
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            self.variables = Some(variables);
+            Ok(())
+        } else {
+            Err(meta.error("expected `topic`, `lock_duration` or `variables`"))
+        }
+    }
+}
+
+pub(crate) fn expand(args: ExternalTaskArgs, item: ItemFn) -> syn::Result<TokenStream> {
+    let Some(topic) = args.topic else {
+        return Err(syn::Error::new_spanned(
+            &item.sig.ident,
+            "missing `topic` of the external task",
+        ));
+    };
+    let lock_duration = match args.lock_duration {
+        Some(lock_duration) => quote! { ::std::option::Option::Some(#lock_duration) },
+        None => quote! { ::std::option::Option::None },
+    };
+    let variables = match args.variables {
+        Some(variables) => quote! { ::std::option::Option::Some(&[#(#variables),*]) },
+        None => quote! { ::std::option::Option::None },
+    };
+    let ident = &item.sig.ident;
+
+    Ok(quote! {
+        #item
+
+        ::camunda_external_task_client_rust::__private::submit_external_task!(
+            #topic,
+            #lock_duration,
+            #variables,
+            #ident
+        );
+    })
+}
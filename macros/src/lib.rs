@@ -0,0 +1,40 @@
+//! Procedural macros of `camunda-external-task-client-rust`, re-exported by its `macros`
+//! feature.
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput, ItemFn};
+
+mod external_task;
+mod process_variables;
+
+/// Derives `ProcessVariables` for a struct with named fields.
+///
+/// Each field is mapped to the variable with the same name. The `variable` attribute
+/// overrides the name, forces a type (`json` stores the field as serialized JSON) and marks
+/// fields which are written as local variables:
+///
+/// `#[variable(name = "creditScores", type = "json", local)]`
+///
+/// Forced types must fit the field, see `Variable::from_serializable_as`. Types which can
+/// never fit, like `date` for an `i32` field, are rejected at compile time.
+#[proc_macro_derive(ProcessVariables, attributes(variable))]
+pub fn derive_process_variables(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    process_variables::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Registers a handler function for `Client::register_all`, which subscribes to `topic`
+/// with the given options:
+///
+/// `#[external_task(topic = "invoiceCreator", lock_duration = 60000, variables = ["amount"])]`
+#[proc_macro_attribute]
+pub fn external_task(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut args = external_task::ExternalTaskArgs::default();
+    let parser = syn::meta::parser(|meta| args.parse(meta));
+    parse_macro_input!(attr with parser);
+    let item = parse_macro_input!(item as ItemFn);
+    external_task::expand(args, item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
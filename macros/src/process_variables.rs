@@ -0,0 +1,253 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, GenericArgument, LitStr, PathArguments, Type};
+
+const VARIABLE_TYPES: [&str; 13] = [
+    "String", "Boolean", "Short", "Integer", "Long", "Double", "Date", "Json", "Xml", "Object",
+    "File", "Bytes", "Null",
+];
+
+struct VariableField {
+    ident: syn::Ident,
+    ty: syn::Type,
+    name: String,
+    variable_type: Option<syn::Ident>,
+    local: bool,
+}
+
+pub(crate) fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "ProcessVariables can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "ProcessVariables can only be derived for structs with named fields",
+        ));
+    };
+    let fields = fields
+        .named
+        .iter()
+        .map(parse_field)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let names = fields.iter().map(|field| &field.name);
+    let reads = fields.iter().map(|field| {
+        let VariableField {
+            ident, ty, name, ..
+        } = field;
+        quote! {
+            #ident: ::camunda_external_task_client_rust::__private::read_variable::<#ty>(variables, #name)?
+        }
+    });
+    let writes = |local: bool| {
+        fields
+            .iter()
+            .filter(move |field| field.local == local)
+            .map(|field| {
+                let VariableField {
+                    ident,
+                    name,
+                    variable_type,
+                    ..
+                } = field;
+                let variable_type = match variable_type {
+                    Some(variable_type) => quote! {
+                        ::std::option::Option::Some(::camunda_external_task_client_rust::VariableType::#variable_type)
+                    },
+                    None => quote! { ::std::option::Option::None },
+                };
+                quote! {
+                    ::camunda_external_task_client_rust::__private::write_variable(
+                        &mut variables,
+                        #name,
+                        &self.#ident,
+                        #variable_type,
+                    )?;
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let variable_writes = writes(false);
+    let local_variable_writes = writes(true);
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::camunda_external_task_client_rust::ProcessVariables for #ident #ty_generics #where_clause {
+            fn variable_names() -> ::std::vec::Vec<::std::string::String> {
+                ::std::vec![#(::std::string::String::from(#names)),*]
+            }
+
+            fn from_variables(
+                variables: &::std::collections::HashMap<::std::string::String, ::camunda_external_task_client_rust::Variable>,
+            ) -> ::camunda_external_task_client_rust::Result<Self> {
+                ::std::result::Result::Ok(Self {
+                    #(#reads),*
+                })
+            }
+
+            fn to_variables(
+                &self,
+            ) -> ::camunda_external_task_client_rust::Result<::std::collections::HashMap<::std::string::String, ::camunda_external_task_client_rust::Variable>> {
+                #[allow(unused_mut)]
+                let mut variables = ::std::collections::HashMap::new();
+                #(#variable_writes)*
+                ::std::result::Result::Ok(variables)
+            }
+
+            fn to_local_variables(
+                &self,
+            ) -> ::camunda_external_task_client_rust::Result<::std::collections::HashMap<::std::string::String, ::camunda_external_task_client_rust::Variable>> {
+                #[allow(unused_mut)]
+                let mut variables = ::std::collections::HashMap::new();
+                #(#local_variable_writes)*
+                ::std::result::Result::Ok(variables)
+            }
+        }
+    })
+}
+
+fn parse_field(field: &syn::Field) -> syn::Result<VariableField> {
+    let ident = field.ident.clone().expect("named field");
+    let mut variable_field = VariableField {
+        name: ident.to_string(),
+        ty: field.ty.clone(),
+        variable_type: None,
+        local: false,
+        ident,
+    };
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("variable"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                variable_field.name = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else if meta.path.is_ident("type") {
+                let lit = meta.value()?.parse::<LitStr>()?;
+                let variable_type = VARIABLE_TYPES
+                    .iter()
+                    .find(|variable_type| variable_type.eq_ignore_ascii_case(&lit.value()))
+                    .ok_or_else(|| syn::Error::new_spanned(&lit, "unknown variable type"))?;
+                check_type(&field.ty, variable_type, &lit)?;
+                variable_field.variable_type = Some(syn::Ident::new(variable_type, lit.span()));
+                Ok(())
+            } else if meta.path.is_ident("local") {
+                variable_field.local = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `name`, `type` or `local`"))
+            }
+        })?;
+    }
+    Ok(variable_field)
+}
+
+/// Rejects variable types which the field can never be stored as. Fields of types which are
+/// not known here are checked when the variables are written.
+fn check_type(ty: &Type, variable_type: &str, lit: &LitStr) -> syn::Result<()> {
+    if matches!(variable_type, "Object" | "File" | "Null") {
+        return Err(syn::Error::new_spanned(
+            lit,
+            format!("fields cannot be stored as {variable_type} variables, use `json` instead"),
+        ));
+    }
+    let Some(inferred) = inferred_type(ty) else {
+        return Ok(());
+    };
+    let storable = match variable_type {
+        "String" | "Date" | "Xml" | "Bytes" => inferred == "String",
+        "Boolean" => inferred == "Boolean",
+        "Short" => inferred == "Short",
+        "Integer" => matches!(inferred, "Short" | "Integer"),
+        "Long" => matches!(inferred, "Short" | "Integer" | "Long"),
+        "Double" => matches!(inferred, "Short" | "Integer" | "Long" | "Double"),
+        _ => true,
+    };
+    if storable {
+        Ok(())
+    } else {
+        Err(syn::Error::new_spanned(
+            lit,
+            format!("cannot store {inferred} field as {variable_type} variable"),
+        ))
+    }
+}
+
+/// Variable type inferred for well known field types, looking through `Option` and
+/// references.
+fn inferred_type(ty: &Type) -> Option<&'static str> {
+    match ty {
+        Type::Reference(reference) => inferred_type(&reference.elem),
+        Type::Paren(paren) => inferred_type(&paren.elem),
+        Type::Group(group) => inferred_type(&group.elem),
+        Type::Array(_) | Type::Slice(_) | Type::Tuple(_) => Some("Json"),
+        Type::Path(path) if path.qself.is_none() => {
+            let segment = path.path.segments.last()?;
+            let inferred = match segment.ident.to_string().as_str() {
+                "Option" => return inner_type(&segment.arguments).and_then(inferred_type),
+                "String" | "str" | "char" => "String",
+                "bool" => "Boolean",
+                "i8" | "i16" | "u8" => "Short",
+                "i32" | "u16" => "Integer",
+                "i64" | "u32" | "u64" => "Long",
+                "f32" | "f64" => "Double",
+                "Vec" | "VecDeque" | "HashMap" | "BTreeMap" | "HashSet" | "BTreeSet" => "Json",
+                _ => return None,
+            };
+            Some(inferred)
+        }
+        _ => None,
+    }
+}
+
+fn inner_type(arguments: &PathArguments) -> Option<&Type> {
+    let PathArguments::AngleBracketed(arguments) = arguments else {
+        return None;
+    };
+    match arguments.args.first()? {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    fn check(ty: Type, variable_type: &str) -> Result<(), String> {
+        let lit: LitStr = parse_quote!("type");
+        check_type(&ty, variable_type, &lit).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn accepts_compatible_types() {
+        assert!(check(parse_quote!(String), "Date").is_ok());
+        assert!(check(parse_quote!(Option<&'a str>), "Xml").is_ok());
+        assert!(check(parse_quote!(i32), "Long").is_ok());
+        assert!(check(parse_quote!(u8), "Double").is_ok());
+        assert!(check(parse_quote!(Vec<String>), "Json").is_ok());
+        assert!(check(parse_quote!(Amount), "Long").is_ok());
+    }
+
+    #[test]
+    fn rejects_impossible_types() {
+        assert_eq!(
+            check(parse_quote!(String), "Boolean"),
+            Err("cannot store String field as Boolean variable".to_string())
+        );
+        assert!(check(parse_quote!(Option<i32>), "Date").is_err());
+        assert!(check(parse_quote!(i64), "Integer").is_err());
+        assert!(check(parse_quote!(Vec<u8>), "Bytes").is_err());
+        assert!(check(parse_quote!(Amount), "Object").is_err());
+    }
+}
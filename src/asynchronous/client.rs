@@ -0,0 +1,490 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use log::{error, trace, warn};
+use tokio::runtime::Handle;
+use tokio::sync::oneshot::error::TryRecvError;
+use tokio::sync::{oneshot, Notify, Semaphore};
+use tokio::task::JoinHandle;
+
+use crate::client::backoff::PollCounter;
+use crate::client::lock_extender::{LockExtender, TICK};
+use crate::client::panic::catch_panic_async;
+use crate::client::InactiveOnDrop;
+use crate::{
+    AsyncEngineService, AsyncTaskContext, AsyncTaskHandler, BackoffStrategy, CancellationReason,
+    CancellationToken, Config, Error, FetchAndLockRequest, FixedBackoff, HandlerError,
+    OrphanPolicy, Result, SubscriptionBuilder, SubscriptionManager, Task,
+};
+
+/// Camunda external task client running on tokio.
+///
+/// Handlers are spawned as tokio tasks, at most `Config::concurrency` at the same time. New
+/// tasks are only fetched while there is capacity left. Tasks which have to wait for their
+/// group of the `ExecutionStrategy` or for the `max_concurrency` of their subscription count
+/// towards the concurrency.
+pub struct AsyncClient {
+    config: Arc<Config>,
+    engine_service: Arc<AsyncEngineService>,
+    subscription_manager: Arc<RwLock<SubscriptionManager<dyn AsyncTaskHandler>>>,
+    backoff_strategy: Arc<dyn BackoffStrategy>,
+    lock_extender: Arc<LockExtender>,
+    running: Arc<Mutex<HashMap<String, CancellationToken>>>,
+    is_active: Arc<RwLock<bool>>,
+    /// Interrupts the sleep of the polling loop between two fetches.
+    wake: Arc<Notify>,
+    poll_join_handle: Option<JoinHandle<()>>,
+}
+
+impl AsyncClient {
+    /// Creates the client. If `Config::auto_poll` is set, polling is started on the current
+    /// tokio runtime. Fails for `OrphanPolicy::Fallback`, whose handler cannot run here.
+    pub fn new(config: Config) -> Result<Self> {
+        if matches!(config.orphan_policy(), OrphanPolicy::Fallback(_)) {
+            return Err(Error::ClientError(
+                "the async client has no fallback handler".to_string(),
+            ));
+        }
+        let config = Arc::new(config);
+        let engine_service = Arc::new(AsyncEngineService::new(Arc::clone(&config))?);
+        let backoff_strategy = config.backoff_strategy().cloned().unwrap_or_else(|| {
+            Arc::new(FixedBackoff::new(Duration::from_millis(config.interval())))
+        });
+
+        let mut instance = Self {
+            subscription_manager: Arc::new(RwLock::new(SubscriptionManager::new())),
+            lock_extender: Arc::new(LockExtender::new()),
+            running: Arc::new(Mutex::new(HashMap::new())),
+            is_active: Arc::new(RwLock::new(false)),
+            wake: Arc::new(Notify::new()),
+            poll_join_handle: None,
+            backoff_strategy,
+            config,
+            engine_service,
+        };
+
+        if instance.config.auto_poll() {
+            instance.poll_join_handle = Some(instance.start()?);
+        }
+
+        Ok(instance)
+    }
+
+    pub fn engine_service(&self) -> &Arc<AsyncEngineService> {
+        &self.engine_service
+    }
+
+    pub fn topic_subscription_manager(
+        &self,
+    ) -> &Arc<RwLock<SubscriptionManager<dyn AsyncTaskHandler>>> {
+        &self.subscription_manager
+    }
+
+    /// Spawns the polling loop on the current tokio runtime.
+    pub fn start(&mut self) -> Result<JoinHandle<()>> {
+        let runtime = Handle::try_current().map_err(|e| {
+            error!("{e:?}");
+            Error::ClientError("client must be started within a tokio runtime".to_string())
+        })?;
+        {
+            let mut is_active = self.is_active.write().unwrap();
+            if *is_active {
+                return Err(Error::ClientError("client already started".to_string()));
+            }
+            *is_active = true;
+        }
+        trace!("start");
+
+        self.lock_extender.activate()?;
+        runtime.spawn(extend_locks(
+            Arc::clone(&self.lock_extender),
+            Arc::clone(&self.engine_service),
+        ));
+
+        let polling = Polling {
+            config: Arc::clone(&self.config),
+            engine_service: Arc::clone(&self.engine_service),
+            subscription_manager: Arc::clone(&self.subscription_manager),
+            backoff_strategy: Arc::clone(&self.backoff_strategy),
+            lock_extender: Arc::clone(&self.lock_extender),
+            running: Arc::clone(&self.running),
+            is_active: Arc::clone(&self.is_active),
+            wake: Arc::clone(&self.wake),
+            semaphore: Arc::new(Semaphore::new(self.config.concurrency())),
+            groups: Mutex::new(HashMap::new()),
+            topic_semaphores: Mutex::new(HashMap::new()),
+            topic_tasks: Arc::new(Mutex::new(HashMap::new())),
+        };
+        Ok(runtime.spawn(polling.poll()))
+    }
+
+    /// Stops polling and cancels the tokens of running handlers. Tasks of a fetch which is
+    /// still running are unlocked.
+    pub fn stop(&mut self) {
+        trace!("stop");
+        *self.is_active.write().unwrap() = false;
+        for cancellation_token in self.running.lock().unwrap().values() {
+            cancellation_token.cancel(CancellationReason::Stopped);
+        }
+        self.wake.notify_one();
+        self.lock_extender.stop();
+    }
+
+    pub fn is_active(&self) -> bool {
+        *self.is_active.read().unwrap()
+    }
+
+    pub fn subscribe(&self, topic_name: &str) -> SubscriptionBuilder<dyn AsyncTaskHandler> {
+        SubscriptionBuilder::new(&self.config, &self.subscription_manager, topic_name)
+    }
+
+    pub async fn join(&mut self) -> Result<()> {
+        let Some(poll_join_handle) = self.poll_join_handle.take() else {
+            return Err(Error::ClientError(
+                "client not active, cannot join".to_string(),
+            ));
+        };
+
+        poll_join_handle.await.map_err(|e| {
+            error!("{e:?}");
+            Error::ClientError("could not join client task".to_string())
+        })
+    }
+}
+
+impl SubscriptionBuilder<dyn AsyncTaskHandler> {
+    pub fn handler<T: AsyncTaskHandler + 'static>(&mut self, handler: T) -> &mut Self {
+        self.set_handler(Arc::new(handler))
+    }
+}
+
+struct Polling {
+    config: Arc<Config>,
+    engine_service: Arc<AsyncEngineService>,
+    subscription_manager: Arc<RwLock<SubscriptionManager<dyn AsyncTaskHandler>>>,
+    backoff_strategy: Arc<dyn BackoffStrategy>,
+    lock_extender: Arc<LockExtender>,
+    running: Arc<Mutex<HashMap<String, CancellationToken>>>,
+    is_active: Arc<RwLock<bool>>,
+    wake: Arc<Notify>,
+    semaphore: Arc<Semaphore>,
+    /// Completion of the last dispatched task of each group of the execution strategy.
+    groups: Mutex<HashMap<String, oneshot::Receiver<()>>>,
+    /// Limits of subscriptions with `max_concurrency`.
+    topic_semaphores: Mutex<HashMap<String, Arc<Semaphore>>>,
+    /// Number of dispatched tasks per topic, which wait for or run in their handler.
+    topic_tasks: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl Polling {
+    async fn poll(self) {
+        let _guard = InactiveOnDrop(Arc::clone(&self.is_active));
+        let mut poll_counter = PollCounter::default();
+        while *self.is_active.read().unwrap() {
+            // wait for capacity before fetching
+            if let Ok(permit) = self.semaphore.acquire().await {
+                drop(permit);
+            }
+
+            trace!("polling...");
+            let result = self.fetch_and_dispatch().await;
+            let Some(delay) = poll_counter.next_delay(self.backoff_strategy.as_ref(), &result)
+            else {
+                continue;
+            };
+            trace!("next poll in {delay:?}");
+            // stopping the client wakes the loop up
+            let _ = tokio::time::timeout(delay, self.wake.notified()).await;
+        }
+        trace!("polling stopped");
+    }
+
+    async fn fetch_and_dispatch(&self) -> Result<usize> {
+        let (topics, room) = {
+            let subscription_manager = self.subscription_manager.read().unwrap();
+            let topic_tasks = self.topic_tasks.lock().unwrap();
+            subscription_manager.subscriptions_below_limit(&topic_tasks)
+        };
+        if topics.is_empty() {
+            trace!("no subscription or every topic is at its max_concurrency");
+            return Ok(0);
+        }
+        let capacity = self.semaphore.available_permits().max(1);
+        let max_tasks = room.map_or(capacity, |room| room.min(capacity));
+        let lock_durations: HashMap<_, _> = topics
+            .iter()
+            .map(|topic| (topic.topic_name.to_owned(), topic.lock_duration))
+            .collect();
+        let mut request = FetchAndLockRequest::new(self.config.worker_id());
+        request.topics = topics;
+        request.use_priority = Some(self.config.use_priority());
+        request.max_tasks = self.config.max_tasks().min(max_tasks as u32);
+        request.async_response_timeout = self.config.async_response_timeout();
+        let locked_at = Instant::now();
+        let tasks = match self.engine_service.fetch_and_lock(request).await {
+            Ok(tasks) => tasks,
+            Err(e) => {
+                error!("failed to fetch tasks, {e:?}");
+                if let Some(error_handler) = self.config.error_handler() {
+                    error_handler(&e);
+                }
+                return Err(e);
+            }
+        };
+        let task_count = tasks.len();
+        // forget groups whose last task finished
+        self.groups
+            .lock()
+            .unwrap()
+            .retain(|_, done| matches!(done.try_recv(), Err(TryRecvError::Empty)));
+
+        for task in tasks {
+            let cancellation_token = CancellationToken::new();
+            // keep the lock alive while the task waits for its group or topic limit
+            if let Some(lock_duration) = lock_durations.get(&task.topic_name) {
+                self.lock_extender
+                    .register(&task, *lock_duration, locked_at, &cancellation_token);
+            }
+
+            let (handler_option, auto_extend_lock) = {
+                let subscription_manager = self.subscription_manager.read().unwrap();
+                let auto_extend_lock = subscription_manager
+                    .get_settings(&task.topic_name)
+                    .is_some_and(|settings| settings.auto_extend_lock);
+                (
+                    subscription_manager.get_handler(&task.topic_name),
+                    auto_extend_lock,
+                )
+            };
+            let Some(handler) = handler_option else {
+                self.lock_extender.unregister(&task);
+                self.handle_orphan(&task).await;
+                continue;
+            };
+            let Ok(permit) = Arc::clone(&self.semaphore).acquire_owned().await else {
+                break;
+            };
+            let engine_service = Arc::clone(&self.engine_service);
+            let lock_extender = Arc::clone(&self.lock_extender);
+            let running = Arc::clone(&self.running);
+            if !self.add_running(&task, &cancellation_token) {
+                // fetched while stopping, the task is not executed anymore
+                self.lock_extender.unregister(&task);
+                trace!("unlock task {}", task.id);
+                if let Err(e) = self.engine_service.unlock(&task).await {
+                    error!("failed to unlock task {}, {e:?}", task.id);
+                }
+                continue;
+            }
+            let context =
+                AsyncTaskContext::new(Arc::clone(&engine_service), cancellation_token.clone());
+            let (done, previous) = self.wait_for_group(&task);
+            let topic_semaphore = self.topic_semaphore(&task);
+            let topic_tasks = Arc::clone(&self.topic_tasks);
+            *topic_tasks
+                .lock()
+                .unwrap()
+                .entry(task.topic_name.to_owned())
+                .or_default() += 1;
+            tokio::spawn(async move {
+                if let Some(previous) = previous {
+                    let _ = previous.await;
+                }
+                let topic_permit = match topic_semaphore {
+                    Some(topic_semaphore) => topic_semaphore.acquire_owned().await.ok(),
+                    None => None,
+                };
+                if !auto_extend_lock {
+                    lock_extender.unregister(&task);
+                }
+                if cancellation_token.reason() == Some(CancellationReason::LockLost) {
+                    warn!("lost the lock of task {} while it was waiting", task.id);
+                } else {
+                    let result = catch_panic_async(|| handler.handle(task.clone(), context)).await;
+                    if cancellation_token.reason() == Some(CancellationReason::LockLost) {
+                        warn!("lost the lock of task {}, outcome not reported", task.id);
+                    } else if let Err(e) = engine_service.report_outcome(&task, result).await {
+                        error!("failed to report outcome of task {}, {e:?}", task.id);
+                    }
+                }
+                lock_extender.unregister(&task);
+                running.lock().unwrap().remove(&task.id);
+                finished(&topic_tasks, &task.topic_name);
+                drop(topic_permit);
+                drop(done);
+                drop(permit);
+            });
+        }
+        Ok(task_count)
+    }
+
+    /// Adds the token of a task to the running ones, unless the client is stopped. Checked
+    /// under the lock of the running tokens, so that `AsyncClient::stop` cancels every token
+    /// added before.
+    fn add_running(&self, task: &Task, cancellation_token: &CancellationToken) -> bool {
+        let mut running = self.running.lock().unwrap();
+        let is_active = *self.is_active.read().unwrap();
+        if is_active {
+            running.insert(task.id.to_owned(), cancellation_token.clone());
+        }
+        is_active
+    }
+
+    /// Queues the task behind the previously dispatched task of its group of the execution
+    /// strategy. Returns the sender to drop when the task is done, and the completion of the
+    /// previous task to wait for.
+    fn wait_for_group(&self, task: &Task) -> (oneshot::Sender<()>, Option<oneshot::Receiver<()>>) {
+        let (done, done_receiver) = oneshot::channel();
+        let previous = self
+            .config
+            .execution_strategy()
+            .group(task)
+            .and_then(|group| self.groups.lock().unwrap().insert(group, done_receiver));
+        (done, previous)
+    }
+
+    fn topic_semaphore(&self, task: &Task) -> Option<Arc<Semaphore>> {
+        let max_concurrency = self
+            .subscription_manager
+            .read()
+            .unwrap()
+            .get_settings(&task.topic_name)
+            .and_then(|settings| settings.max_concurrency)?;
+        let mut topic_semaphores = self.topic_semaphores.lock().unwrap();
+        let topic_semaphore = topic_semaphores
+            .entry(task.topic_name.to_owned())
+            .or_insert_with(|| Arc::new(Semaphore::new(max_concurrency)));
+        Some(Arc::clone(topic_semaphore))
+    }
+
+    /// Applies the orphan policy to a task without handler.
+    async fn handle_orphan(&self, task: &Task) {
+        warn!(
+            "no handler for topic {} of task {}",
+            task.topic_name, task.id
+        );
+        let result = match self.config.orphan_policy() {
+            OrphanPolicy::Failure => {
+                let message = format!("no handler for topic {}", task.topic_name);
+                self.engine_service
+                    .report_outcome(task, Err(HandlerError::new(&message)))
+                    .await
+            }
+            // fallback handlers are rejected by `AsyncClient::new`
+            OrphanPolicy::Unlock | OrphanPolicy::Fallback(_) => {
+                trace!("unlock task {}", task.id);
+                self.engine_service.unlock(task).await
+            }
+        };
+        if let Err(e) = result {
+            error!("failed to release task {} without handler, {e:?}", task.id);
+        }
+    }
+}
+
+/// Counts a dispatched task of the topic as done.
+fn finished(topic_tasks: &Mutex<HashMap<String, usize>>, topic_name: &str) {
+    let mut topic_tasks = topic_tasks.lock().unwrap();
+    if let Some(tasks) = topic_tasks.get_mut(topic_name) {
+        *tasks -= 1;
+        if *tasks == 0 {
+            topic_tasks.remove(topic_name);
+        }
+    }
+}
+
+async fn extend_locks(lock_extender: Arc<LockExtender>, engine_service: Arc<AsyncEngineService>) {
+    while lock_extender.is_active() {
+        for (task, lock_duration) in lock_extender.due_tasks() {
+            let extended_at = Instant::now();
+            let result = engine_service
+                .extend_lock(&task, lock_duration.into())
+                .await;
+            lock_extender.extended(&task, lock_duration, extended_at, result);
+        }
+        tokio::time::sleep(TICK).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ExecutionStrategy;
+
+    fn polling(execution_strategy: ExecutionStrategy) -> Polling {
+        let config =
+            Arc::new(Config::new("http://localhost").with_execution_strategy(execution_strategy));
+        Polling {
+            engine_service: Arc::new(AsyncEngineService::new(Arc::clone(&config)).unwrap()),
+            subscription_manager: Arc::new(RwLock::new(SubscriptionManager::new())),
+            backoff_strategy: Arc::new(FixedBackoff::new(Duration::ZERO)),
+            lock_extender: Arc::new(LockExtender::new()),
+            running: Arc::new(Mutex::new(HashMap::new())),
+            is_active: Arc::new(RwLock::new(true)),
+            wake: Arc::new(Notify::new()),
+            semaphore: Arc::new(Semaphore::new(config.concurrency())),
+            groups: Mutex::new(HashMap::new()),
+            topic_semaphores: Mutex::new(HashMap::new()),
+            topic_tasks: Arc::new(Mutex::new(HashMap::new())),
+            config,
+        }
+    }
+
+    fn task(activity_id: &str) -> Task {
+        Task {
+            activity_id: activity_id.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn queues_tasks_of_same_group() {
+        let polling = polling(ExecutionStrategy::PerActivity);
+
+        let (first_done, first_previous) = polling.wait_for_group(&task("a"));
+        let (_, second_previous) = polling.wait_for_group(&task("a"));
+        let (_, other_previous) = polling.wait_for_group(&task("b"));
+
+        assert!(first_previous.is_none());
+        assert!(other_previous.is_none());
+        let mut second_previous = second_previous.unwrap();
+        assert_eq!(second_previous.try_recv(), Err(TryRecvError::Empty));
+        drop(first_done);
+        assert_eq!(second_previous.try_recv(), Err(TryRecvError::Closed));
+    }
+
+    #[test]
+    fn runs_parallel_tasks_without_waiting() {
+        let polling = polling(ExecutionStrategy::Parallel);
+
+        polling.wait_for_group(&task("a"));
+
+        assert!(polling.wait_for_group(&task("a")).1.is_none());
+    }
+
+    #[test]
+    fn adds_no_running_tasks_after_stop() {
+        let polling = polling(ExecutionStrategy::Parallel);
+        let cancellation_token = CancellationToken::new();
+
+        assert!(polling.add_running(&task("a"), &cancellation_token));
+        *polling.is_active.write().unwrap() = false;
+        assert!(!polling.add_running(&task("b"), &cancellation_token));
+
+        assert_eq!(polling.running.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn rejects_fallback_handler() {
+        let config = Config::new("http://localhost")
+            .with_auto_poll(false)
+            .with_fallback_handler(|| Ok(crate::TaskOutcome::complete()));
+
+        assert!(matches!(
+            AsyncClient::new(config),
+            Err(Error::ClientError(_))
+        ));
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use log::{error, trace};
+use reqwest::{Client, ClientBuilder};
+
+use crate::client::outcome::outcome_request;
+use crate::service::request::{self, EngineRequest};
+use crate::{Config, Error, FetchAndLockRequest, HandlerResult, Result, Task, Variable};
+
+/// Async counterpart of `EngineService`, based on the async reqwest client.
+pub struct AsyncEngineService {
+    config: Arc<Config>,
+    client: Client,
+}
+
+impl AsyncEngineService {
+    pub fn new(config: Arc<Config>) -> Result<Self> {
+        let client = ClientBuilder::new()
+            .timeout(request::timeout(&config))
+            .build()
+            .map_err(Error::ClientConfigurationError)?;
+        Ok(Self { config, client })
+    }
+
+    async fn send(&self, request: EngineRequest) -> Result<String> {
+        let mut request_builder = self.client.post(request.url(&self.config));
+        if let Some(body) = &request.body {
+            request_builder = request_builder.json(body);
+        }
+        let request = request_builder.build().map_err(Error::RequestError)?;
+        trace!("{request:?}");
+        trace!("{:?}", request.body());
+        let response = self.client.execute(request).await.map_err(|e| {
+            error!("{e:?}");
+            Error::RequestError(e)
+        })?;
+        trace!("{response:?}");
+
+        let status = response.status();
+        let status_error = response.error_for_status_ref().err();
+        let response_text = response.text().await.map_err(Error::RequestError)?;
+        trace!("{response_text:?}");
+        request::response_text(status, status_error, response_text)
+    }
+
+    pub async fn complete(
+        &self,
+        task: &Task,
+        variables: Option<HashMap<String, Variable>>,
+        local_variables: Option<HashMap<String, Variable>>,
+    ) -> Result<()> {
+        let request = EngineRequest::complete(&self.config, task, variables, local_variables)?;
+        self.send(request).await.map(|_| ())
+    }
+
+    /// Reports a business error which can be caught by a BPMN error boundary event.
+    pub async fn handle_bpmn_error(
+        &self,
+        task: &Task,
+        error_code: &str,
+        error_message: Option<&str>,
+        variables: Option<HashMap<String, Variable>>,
+    ) -> Result<()> {
+        let request = EngineRequest::handle_bpmn_error(
+            &self.config,
+            task,
+            error_code,
+            error_message,
+            variables,
+        )?;
+        self.send(request).await.map(|_| ())
+    }
+
+    /// Reports a failure to execute the task. If `retries` is 0 an incident is created,
+    /// otherwise the task can be fetched again after `retry_timeout` milliseconds.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn handle_failure(
+        &self,
+        task: &Task,
+        error_message: &str,
+        error_details: Option<&str>,
+        retries: u32,
+        retry_timeout: u64,
+        variables: Option<HashMap<String, Variable>>,
+        local_variables: Option<HashMap<String, Variable>>,
+    ) -> Result<()> {
+        let request = EngineRequest::handle_failure(
+            &self.config,
+            task,
+            error_message,
+            error_details,
+            retries,
+            retry_timeout,
+            variables,
+            local_variables,
+        )?;
+        self.send(request).await.map(|_| ())
+    }
+
+    /// Extends the lock of the task by `new_duration` milliseconds, counted from now.
+    pub async fn extend_lock(&self, task: &Task, new_duration: u64) -> Result<()> {
+        let request = EngineRequest::extend_lock(&self.config, task, new_duration)?;
+        self.send(request).await.map(|_| ())
+    }
+
+    /// Releases the lock of the task, so that it can be fetched again immediately.
+    pub async fn unlock(&self, task: &Task) -> Result<()> {
+        self.send(EngineRequest::unlock(task)).await.map(|_| ())
+    }
+
+    /// Async counterpart of `EngineService::report_outcome`.
+    pub(crate) async fn report_outcome(&self, task: &Task, result: HandlerResult) -> Result<()> {
+        self.send(outcome_request(&self.config, task, result)?)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn fetch_and_lock(&self, request: FetchAndLockRequest) -> Result<Vec<Task>> {
+        let response_text = self.send(EngineRequest::fetch_and_lock(&request)?).await?;
+        request::parse_tasks(&response_text)
+    }
+}
@@ -0,0 +1,77 @@
+//! Async client and engine service based on tokio, enabled by the `async` feature.
+//!
+//! ```no_run
+//! use camunda_external_task_client_rust::*;
+//!
+//! async fn handler(task: Task, context: AsyncTaskContext) -> HandlerResult {
+//!     // your code, checking context.cancellation_token() between steps
+//!     Ok(TaskOutcome::complete())
+//! }
+//!
+//! # async fn run() -> Result<()> {
+//! let config = Config::new("http://...").with_concurrency(20);
+//! let mut client = AsyncClient::new(config)?;
+//! client.subscribe("topic").handler(handler).open()?;
+//! client.join().await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+pub use client::*;
+pub use engine_service::*;
+
+use crate::{CancellationToken, HandlerResult, Task};
+
+mod client;
+mod engine_service;
+
+pub type HandlerFuture = Pin<Box<dyn Future<Output = HandlerResult> + Send>>;
+
+/// Passed to async handlers along with their task.
+#[derive(Clone)]
+pub struct AsyncTaskContext {
+    engine_service: Arc<AsyncEngineService>,
+    cancellation_token: CancellationToken,
+}
+
+impl AsyncTaskContext {
+    pub(crate) fn new(
+        engine_service: Arc<AsyncEngineService>,
+        cancellation_token: CancellationToken,
+    ) -> Self {
+        Self {
+            engine_service,
+            cancellation_token,
+        }
+    }
+
+    pub fn engine_service(&self) -> &Arc<AsyncEngineService> {
+        &self.engine_service
+    }
+
+    /// Cancelled when the client stops or the lock of the task is lost.
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation_token
+    }
+}
+
+/// Handles the tasks fetched for a subscribed topic by the async client.
+///
+/// Implemented for async functions and closures with the signature
+/// `Fn(Task, AsyncTaskContext) -> impl Future<Output = HandlerResult>`.
+pub trait AsyncTaskHandler: Send + Sync {
+    fn handle(&self, task: Task, context: AsyncTaskContext) -> HandlerFuture;
+}
+
+impl<F, Fut> AsyncTaskHandler for F
+where
+    F: Fn(Task, AsyncTaskContext) -> Fut + Send + Sync,
+    Fut: Future<Output = HandlerResult> + Send + 'static,
+{
+    fn handle(&self, task: Task, context: AsyncTaskContext) -> HandlerFuture {
+        Box::pin(self(task, context))
+    }
+}
@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use crate::{Error, Result};
+
+/// Result of a fetch, used to calculate the time to wait before the next one.
+pub enum PollResult<'a> {
+    Fetched(usize),
+    Failed(&'a Error),
+}
+
+/// Calculates the time to wait between two fetches.
+///
+/// The clients ask only after fetches without tasks or with an error. After fetching tasks
+/// they fetch again as soon as there is capacity for more.
+///
+/// `attempt` is the number of consecutive polls, including the current one, which ended
+/// like the current one, either without tasks or with an error.
+pub trait BackoffStrategy: Send + Sync {
+    fn delay(&self, result: &PollResult, attempt: u32) -> Duration;
+}
+
+/// Counts consecutive empty and failed polls of a polling loop.
+#[derive(Default)]
+pub(crate) struct PollCounter {
+    empty_polls: u32,
+    failed_polls: u32,
+}
+
+impl PollCounter {
+    /// Returns the time to wait before the next fetch, or `None` if tasks were fetched.
+    /// Fetching waits for capacity, so there is no need to back off then.
+    pub fn next_delay(
+        &mut self,
+        backoff_strategy: &dyn BackoffStrategy,
+        result: &Result<usize>,
+    ) -> Option<Duration> {
+        let (poll_result, attempt) = match result {
+            Ok(0) => {
+                self.empty_polls += 1;
+                self.failed_polls = 0;
+                (PollResult::Fetched(0), self.empty_polls)
+            }
+            Ok(_) => {
+                self.empty_polls = 0;
+                self.failed_polls = 0;
+                return None;
+            }
+            Err(e) => {
+                self.empty_polls = 0;
+                self.failed_polls += 1;
+                (PollResult::Failed(e), self.failed_polls)
+            }
+        };
+        Some(backoff_strategy.delay(&poll_result, attempt))
+    }
+}
+
+/// Waits the same time after each fetch. Used with `Config::interval` by default.
+pub struct FixedBackoff {
+    interval: Duration,
+}
+
+impl FixedBackoff {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval }
+    }
+}
+
+impl BackoffStrategy for FixedBackoff {
+    fn delay(&self, _result: &PollResult, _attempt: u32) -> Duration {
+        self.interval
+    }
+}
+
+/// Fetches again immediately while there are tasks and waits exponentially longer for each
+/// empty or failed fetch in a row. Errors use the same times unless configured separately.
+pub struct ExponentialBackoff {
+    initial: Duration,
+    max: Duration,
+    error_initial: Duration,
+    error_max: Duration,
+    multiplier: f64,
+}
+
+impl ExponentialBackoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            error_initial: initial,
+            error_max: max,
+            multiplier: 2.0,
+        }
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+    pub fn with_error_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.error_initial = initial;
+        self.error_max = max;
+        self
+    }
+
+    fn exponential(&self, initial: Duration, max: Duration, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let millis = initial.as_millis() as f64 * self.multiplier.powi(exponent);
+        Duration::from_millis(millis.min(max.as_millis() as f64) as u64)
+    }
+}
+
+impl BackoffStrategy for ExponentialBackoff {
+    fn delay(&self, result: &PollResult, attempt: u32) -> Duration {
+        match result {
+            PollResult::Fetched(0) => self.exponential(self.initial, self.max, attempt),
+            PollResult::Fetched(_) => Duration::ZERO,
+            PollResult::Failed(_) => self.exponential(self.error_initial, self.error_max, attempt),
+        }
+    }
+}
+
+/// Randomizes the delay of another strategy by up to `factor` in both directions, so that
+/// many workers do not fetch at the same time.
+pub struct JitterBackoff<B: BackoffStrategy> {
+    inner: B,
+    factor: f64,
+}
+
+impl<B: BackoffStrategy> JitterBackoff<B> {
+    pub fn new(inner: B, factor: f64) -> Self {
+        Self {
+            inner,
+            factor: factor.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl<B: BackoffStrategy> BackoffStrategy for JitterBackoff<B> {
+    fn delay(&self, result: &PollResult, attempt: u32) -> Duration {
+        let delay = self.inner.delay(result, attempt);
+        let jitter = (fastrand::f64() * 2.0 - 1.0) * self.factor;
+        delay.mul_f64(1.0 + jitter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn fixed_always_waits_interval() {
+        let backoff = FixedBackoff::new(millis(500));
+        let error = Error::ClientError("failed".into());
+
+        assert_eq!(backoff.delay(&PollResult::Fetched(0), 3), millis(500));
+        assert_eq!(backoff.delay(&PollResult::Fetched(5), 0), millis(500));
+        assert_eq!(backoff.delay(&PollResult::Failed(&error), 1), millis(500));
+    }
+
+    #[test]
+    fn exponential_doubles_up_to_max() {
+        let backoff = ExponentialBackoff::new(millis(100), millis(1000));
+        let empty = PollResult::Fetched(0);
+
+        assert_eq!(backoff.delay(&empty, 1), millis(100));
+        assert_eq!(backoff.delay(&empty, 2), millis(200));
+        assert_eq!(backoff.delay(&empty, 4), millis(800));
+        assert_eq!(backoff.delay(&empty, 5), millis(1000));
+        assert_eq!(backoff.delay(&empty, u32::MAX), millis(1000));
+    }
+
+    #[test]
+    fn exponential_fetches_again_immediately_with_tasks() {
+        let backoff = ExponentialBackoff::new(millis(100), millis(1000));
+
+        assert_eq!(backoff.delay(&PollResult::Fetched(3), 0), Duration::ZERO);
+    }
+
+    #[test]
+    fn exponential_uses_multiplier_and_error_backoff() {
+        let backoff = ExponentialBackoff::new(millis(100), millis(1000))
+            .with_multiplier(3.0)
+            .with_error_backoff(millis(500), millis(10_000));
+        let error = Error::ClientError("failed".into());
+        let failed = PollResult::Failed(&error);
+
+        assert_eq!(backoff.delay(&PollResult::Fetched(0), 3), millis(900));
+        assert_eq!(backoff.delay(&failed, 1), millis(500));
+        assert_eq!(backoff.delay(&failed, 2), millis(1500));
+        assert_eq!(backoff.delay(&failed, 5), millis(10_000));
+    }
+
+    #[test]
+    fn jitter_stays_within_factor() {
+        let backoff = JitterBackoff::new(ExponentialBackoff::new(millis(1000), millis(1000)), 0.2);
+
+        for _ in 0..100 {
+            let delay = backoff.delay(&PollResult::Fetched(0), 1);
+            assert!(delay >= millis(800) && delay <= millis(1200), "{delay:?}");
+        }
+    }
+
+    #[test]
+    fn jitter_factor_is_clamped() {
+        let backoff = JitterBackoff::new(ExponentialBackoff::new(millis(1000), millis(1000)), 5.0);
+
+        for _ in 0..100 {
+            assert!(backoff.delay(&PollResult::Fetched(0), 1) <= millis(2000));
+        }
+    }
+
+    #[test]
+    fn poll_counter_counts_consecutive_polls() {
+        let backoff = ExponentialBackoff::new(millis(100), millis(1000))
+            .with_error_backoff(millis(500), millis(10_000));
+        let mut counter = PollCounter::default();
+        let failed = Err(Error::ClientError("failed".into()));
+
+        assert_eq!(counter.next_delay(&backoff, &Ok(0)), Some(millis(100)));
+        assert_eq!(counter.next_delay(&backoff, &Ok(0)), Some(millis(200)));
+        assert_eq!(counter.next_delay(&backoff, &failed), Some(millis(500)));
+        assert_eq!(counter.next_delay(&backoff, &failed), Some(millis(1000)));
+        assert_eq!(counter.next_delay(&backoff, &Ok(2)), None);
+        assert_eq!(counter.next_delay(&backoff, &Ok(0)), Some(millis(100)));
+    }
+}
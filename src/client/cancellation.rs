@@ -0,0 +1,127 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// Why the execution of a task was cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancellationReason {
+    /// The client was stopped or is shutting down.
+    Stopped,
+    /// The lock of the task expired or could not be extended, it was unlocked, deleted or taken
+    /// over by another worker. The outcome of the task is not reported anymore.
+    LockLost,
+}
+
+/// Signals a running handler that it should stop working on its task.
+///
+/// Cancellation is cooperative, handlers check `is_cancelled` or wait with `wait_timeout`
+/// between steps of long running work. Async handlers can await `cancelled`.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<CancellationState>);
+
+#[derive(Default)]
+struct CancellationState {
+    reason: Mutex<Option<CancellationReason>>,
+    cancelled: Condvar,
+    #[cfg(feature = "async")]
+    notify: tokio::sync::Notify,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token, the first reason is kept.
+    pub fn cancel(&self, reason: CancellationReason) {
+        let mut current = self.lock_reason();
+        if current.is_none() {
+            *current = Some(reason);
+            self.0.cancelled.notify_all();
+            #[cfg(feature = "async")]
+            self.0.notify.notify_waiters();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.lock_reason().is_some()
+    }
+
+    pub fn reason(&self) -> Option<CancellationReason> {
+        *self.lock_reason()
+    }
+
+    /// Blocks until the token is cancelled or `timeout` passed, returns whether it is
+    /// cancelled. Use it instead of `thread::sleep` in handlers.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let reason = self.lock_reason();
+        let (reason, _) = self
+            .0
+            .cancelled
+            .wait_timeout_while(reason, timeout, |reason| reason.is_none())
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        reason.is_some()
+    }
+
+    /// Completes when the token is cancelled.
+    #[cfg(feature = "async")]
+    pub async fn cancelled(&self) {
+        // registered before checking, so a concurrent cancel is not missed
+        let notified = self.0.notify.notified();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+
+    fn lock_reason(&self) -> std::sync::MutexGuard<'_, Option<CancellationReason>> {
+        self.0
+            .reason
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn keeps_first_reason() {
+        let token = CancellationToken::new();
+        assert!(!token.is_cancelled());
+
+        token.cancel(CancellationReason::LockLost);
+        token.cancel(CancellationReason::Stopped);
+
+        assert_eq!(token.reason(), Some(CancellationReason::LockLost));
+    }
+
+    #[test]
+    fn wakes_up_waiting_thread() {
+        let token = CancellationToken::new();
+        let cancelling = token.clone();
+        thread::spawn(move || cancelling.cancel(CancellationReason::Stopped));
+
+        assert!(token.wait_timeout(Duration::from_secs(10)));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn completes_cancelled_future() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let token = CancellationToken::new();
+        let cancelling = token.clone();
+
+        runtime.block_on(async {
+            tokio::spawn(async move { cancelling.cancel(CancellationReason::Stopped) });
+            token.cancelled().await;
+            token.cancelled().await;
+        });
+
+        assert!(token.is_cancelled());
+    }
+}
@@ -1,36 +1,51 @@
 //! TODO: add docs
 //!
-//! ```
+//! ```no_run
 //! use camunda_external_task_client_rust::*;
 //!
+//! # fn main() -> Result<()> {
 //! let config = Config::new("http://...");
+//! let client = Client::new(config)?;
 //! client
 //!     .subscribe("topic")
 //!     .handler(|task: &Task, engine_service: &EngineService| {
 //!         // your code
+//!         Ok(TaskOutcome::complete())
 //!     })
 //!     .open()?;
+//! # Ok(())
+//! # }
 //! ```
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Condvar, Mutex, PoisonError, RwLock};
 use std::thread;
 use std::thread::JoinHandle;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use log::{error, trace};
-use rayon::prelude::*;
+use log::{error, trace, warn};
 
+use crate::client::backoff::PollCounter;
+use crate::client::executor::{timestamp_millis, Executor, Job};
+use crate::client::lock_extender::LockExtender;
+use crate::client::panic::catch_panic;
+use crate::client::state::StateMap;
+use crate::client::InactiveOnDrop;
 use crate::{
-    Config, EngineService, Error, FetchAndLockRequest, Result, SubscriptionBuilder,
-    SubscriptionManager, Task,
+    BackoffStrategy, CancellationReason, CancellationToken, Config, EngineService, Error,
+    FetchAndLockRequest, FixedBackoff, HandlerError, OrphanPolicy, Result, SubscriptionBuilder,
+    SubscriptionManager, Task, TaskContext, TaskHandler,
 };
 
 /// Camunda external task client.
 pub struct Client {
     config: Arc<Config>,
     engine_service: Arc<EngineService>,
-    subscription_manager: Arc<RwLock<SubscriptionManager>>,
+    subscription_manager: Arc<RwLock<SubscriptionManager<dyn TaskHandler>>>,
     task_polling: TaskPolling,
+    lock_extender: Arc<LockExtender>,
+    states: Arc<RwLock<StateMap>>,
+    in_flight: Arc<InFlightTasks>,
+    executor: Arc<Executor>,
     poll_join_handle: Option<JoinHandle<()>>,
 }
 
@@ -40,9 +55,16 @@ impl Client {
         let engine_service = EngineService::new(Arc::clone(&config))?;
         let engine_service = Arc::new(engine_service);
         let topic_subscription_manager = Arc::new(RwLock::new(SubscriptionManager::new()));
+        let backoff_strategy = config.backoff_strategy().cloned().unwrap_or_else(|| {
+            Arc::new(FixedBackoff::new(Duration::from_millis(config.interval())))
+        });
 
         let mut instance = Self {
-            task_polling: TaskPolling::new(config.interval()),
+            task_polling: TaskPolling::new(backoff_strategy),
+            lock_extender: Arc::new(LockExtender::new()),
+            states: Arc::new(RwLock::new(StateMap::default())),
+            in_flight: Arc::new(InFlightTasks::default()),
+            executor: Arc::new(Executor::new(config.concurrency())?),
             subscription_manager: topic_subscription_manager,
             poll_join_handle: None,
             config,
@@ -56,62 +78,238 @@ impl Client {
         Ok(instance)
     }
 
+    /// Adds state shared by all handlers, which extract it with `State<S>`. There is one
+    /// state per type `S`, adding another one replaces it.
+    pub fn with_state<S: Send + Sync + 'static>(self, state: S) -> Self {
+        self.states.write().unwrap().insert(state);
+        self
+    }
+
     pub fn engine_service(&self) -> &Arc<EngineService> {
         &self.engine_service
     }
 
-    pub fn topic_subscription_manager(&self) -> &Arc<RwLock<SubscriptionManager>> {
+    pub fn topic_subscription_manager(&self) -> &Arc<RwLock<SubscriptionManager<dyn TaskHandler>>> {
         &self.subscription_manager
     }
 
     pub fn start(&mut self) -> Result<JoinHandle<()>> {
-        let subscription_manager = Arc::clone(&self.subscription_manager);
         let config = Arc::clone(&self.config);
-        let engine_service = Arc::clone(&self.engine_service);
+        let states = Arc::clone(&self.states);
+        let executor = Arc::clone(&self.executor);
+        let is_active = Arc::clone(&self.task_polling.is_active);
+        let dispatch = Arc::new(Dispatch {
+            engine_service: Arc::clone(&self.engine_service),
+            subscription_manager: Arc::clone(&self.subscription_manager),
+            lock_extender: Arc::clone(&self.lock_extender),
+            in_flight: Arc::clone(&self.in_flight),
+            orphan_policy: self.config.orphan_policy().clone(),
+        });
+        self.in_flight.reset();
 
-        self.task_polling.start(move || {
-            // fetch
-            let subscriptions = subscription_manager.read().unwrap().subscriptions();
+        let poll_join_handle = self.task_polling.start(move || {
+            // fetch only as many tasks as there are free workers, for topics below their
+            // max_concurrency
+            let (free_workers, topics, room) = loop {
+                let free_workers = executor.wait_for_capacity(SHUTDOWN_CHECK_INTERVAL);
+                if free_workers > 0 {
+                    let subscription_manager = dispatch
+                        .subscription_manager
+                        .read()
+                        .unwrap_or_else(PoisonError::into_inner);
+                    let (topics, room) =
+                        subscription_manager.subscriptions_below_limit(&executor.topic_jobs());
+                    if !topics.is_empty() {
+                        break (free_workers, topics, room);
+                    }
+                    drop(subscription_manager);
+                    // no subscription or every topic is at its max_concurrency
+                    executor.wait_for_change(SHUTDOWN_CHECK_INTERVAL);
+                }
+                if !*is_active.read().unwrap_or_else(PoisonError::into_inner) {
+                    return Ok(0);
+                }
+            };
+            let max_tasks = room.map_or(free_workers, |room| room.min(free_workers));
+            let lock_durations: HashMap<_, _> = topics
+                .iter()
+                .map(|topic| (topic.topic_name.to_owned(), topic.lock_duration))
+                .collect();
             let mut request = FetchAndLockRequest::new(config.worker_id());
-            request.topics = subscriptions;
+            request.topics = topics;
             request.use_priority = Some(config.use_priority());
-            request.max_tasks = config.max_tasks();
-            let tasks = engine_service.fetch_and_lock(request).unwrap();
-
-            // group tasks by activityId
-            let mut tasks_by_activity: HashMap<String, Vec<Task>> = HashMap::new();
-            tasks.into_iter().for_each(|task| {
-                tasks_by_activity
-                    .entry(task.activity_id.to_owned())
-                    .or_insert_with(Vec::new)
-                    .push(task)
-            });
+            request.max_tasks = config.max_tasks().min(max_tasks as u32);
+            request.async_response_timeout = config.async_response_timeout();
+            let locked_at = Instant::now();
+            let tasks = match dispatch.engine_service.fetch_and_lock(request) {
+                Ok(tasks) => tasks,
+                Err(e) => {
+                    error!("failed to fetch tasks, {e:?}");
+                    if let Some(error_handler) = config.error_handler() {
+                        error_handler(&e);
+                    }
+                    return Err(e);
+                }
+            };
+            let task_count = tasks.len();
 
-            // execute tasks
-            tasks_by_activity.par_iter().for_each(|(_, tasks)| {
+            let Some(cancellation_tokens) = dispatch.in_flight.add(&tasks) else {
+                // fetched while stopping, the tasks are not executed anymore
                 tasks.iter().for_each(|task| {
-                    let subscription_manager = subscription_manager.read().unwrap();
-                    let handler_option = subscription_manager.get_handler(&task.topic_name);
-                    if let Some(handler) = handler_option {
-                        handler(task, &engine_service)
-                    }
+                    unlock(&dispatch.engine_service, &dispatch.lock_extender, task)
                 });
+                return Ok(task_count);
+            };
+
+            // keep locks alive while tasks wait for a worker, execute unregisters the ones
+            // whose subscription does not extend locks automatically
+            tasks.iter().for_each(|task| {
+                if let Some(lock_duration) = lock_durations.get(&task.topic_name) {
+                    let cancellation_token = &cancellation_tokens[&task.id];
+                    dispatch.lock_extender.register(
+                        task,
+                        *lock_duration,
+                        locked_at,
+                        cancellation_token,
+                    );
+                }
             });
-        })
+
+            // execute tasks in the background, grouped by the execution strategy
+            let states = Arc::new(
+                states
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone(),
+            );
+            for task in tasks {
+                let topic_limit = dispatch
+                    .subscription_manager
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .get_settings(&task.topic_name)
+                    .and_then(|settings| settings.max_concurrency);
+                let dispatch = Arc::clone(&dispatch);
+                let states = Arc::clone(&states);
+                executor.submit(Job {
+                    priority: task.priority,
+                    create_time: task.create_time.as_deref().and_then(timestamp_millis),
+                    group: config.execution_strategy().group(&task),
+                    topic: task.topic_name.to_owned(),
+                    topic_limit,
+                    run: Box::new(move || dispatch.execute(&task, &states)),
+                });
+            }
+            Ok(task_count)
+        })?;
+        self.lock_extender.start(Arc::clone(&self.engine_service))?;
+        Ok(poll_join_handle)
     }
 
+    /// Stops fetching and cancels the tokens of fetched tasks, without waiting for them.
+    /// Tasks of a fetch which is still running are unlocked.
     pub fn stop(&mut self) {
         self.task_polling.stop();
+        self.in_flight.close();
+        self.in_flight.cancel(CancellationReason::Stopped);
+        self.wake_polling();
+        self.lock_extender.stop();
+    }
+
+    /// Stops fetching, cancels the tokens of running handlers and waits up to `timeout` for
+    /// them to report their outcome, then joins the polling thread. Fetched tasks whose
+    /// handler has not started yet are unlocked, so that they can be fetched again right away.
+    pub fn shutdown(&mut self, timeout: Duration) -> Result<()> {
+        trace!("shutdown");
+        let deadline = Instant::now() + timeout;
+        self.task_polling.stop();
+        self.wake_polling();
+
+        for task in self.in_flight.drain() {
+            unlock(&self.engine_service, &self.lock_extender, &task);
+        }
+        self.in_flight.cancel(CancellationReason::Stopped);
+        let handlers_finished = self.in_flight.wait_idle(deadline);
+        self.lock_extender.stop();
+        if !handlers_finished {
+            return Err(Error::ClientError(
+                "shutdown timed out waiting for running handlers".to_string(),
+            ));
+        }
+
+        let Some(poll_join_handle) = self.poll_join_handle.take() else {
+            return Ok(());
+        };
+        // the polling thread may still wait for a long polling response
+        while !poll_join_handle.is_finished() && Instant::now() < deadline {
+            thread::sleep(SHUTDOWN_CHECK_INTERVAL);
+        }
+        if !poll_join_handle.is_finished() {
+            self.poll_join_handle = Some(poll_join_handle);
+            return Err(Error::ClientError(
+                "shutdown timed out waiting for the polling thread".to_string(),
+            ));
+        }
+        poll_join_handle.join().map_err(|e| {
+            error!("{e:?}");
+            Error::ClientError("could not join client thread".to_string())
+        })
+    }
+
+    /// Waits for SIGINT or SIGTERM, then shuts the client down like `shutdown`. Returns
+    /// early if polling stops for another reason.
+    #[cfg(feature = "signal")]
+    pub fn shutdown_on_signal(&mut self, timeout: Duration) -> Result<()> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        ctrlc::set_handler(move || {
+            let _ = sender.send(());
+        })
+        .map_err(|e| Error::ClientError(format!("cannot handle signals, {e}")))?;
+
+        loop {
+            match receiver.recv_timeout(SHUTDOWN_CHECK_INTERVAL) {
+                Ok(()) => break,
+                Err(_) if self.is_active() => continue,
+                Err(_) => break,
+            }
+        }
+        self.shutdown(timeout)
+    }
+
+    /// Interrupts the sleep of the polling thread between two fetches.
+    fn wake_polling(&self) {
+        if let Some(poll_join_handle) = &self.poll_join_handle {
+            poll_join_handle.thread().unpark();
+        }
     }
 
     pub fn is_active(&self) -> bool {
         self.task_polling.is_active()
     }
 
-    pub fn subscribe(&self, topic_name: &str) -> SubscriptionBuilder {
+    pub fn subscribe(&self, topic_name: &str) -> SubscriptionBuilder<dyn TaskHandler> {
         SubscriptionBuilder::new(&self.config, &self.subscription_manager, topic_name)
     }
 
+    /// Subscribes to the topics of all handlers annotated with `#[external_task]`.
+    #[cfg(feature = "macros")]
+    pub fn register_all(&self) -> Result<()> {
+        for external_task in inventory::iter::<crate::__private::ExternalTask> {
+            let mut subscription_builder = self.subscribe(external_task.topic);
+            if let Some(lock_duration) = external_task.lock_duration {
+                subscription_builder.lock_duration(lock_duration);
+            }
+            if let Some(variables) = external_task.variables {
+                subscription_builder
+                    .variables(Some(variables.iter().map(|v| v.to_string()).collect()));
+            }
+            subscription_builder
+                .set_handler((external_task.handler)())
+                .open()?;
+        }
+        Ok(())
+    }
+
     pub fn join(&mut self) -> Result<()> {
         if self.poll_join_handle.is_none() {
             return Err(Error::ClientError(
@@ -126,22 +324,194 @@ impl Client {
     }
 }
 
+/// Everything needed to execute fetched tasks on the executor.
+struct Dispatch {
+    engine_service: Arc<EngineService>,
+    subscription_manager: Arc<RwLock<SubscriptionManager<dyn TaskHandler>>>,
+    lock_extender: Arc<LockExtender>,
+    in_flight: Arc<InFlightTasks>,
+    orphan_policy: OrphanPolicy,
+}
+
+impl Dispatch {
+    fn execute(&self, task: &Task, states: &StateMap) {
+        let Some(cancellation_token) = self.in_flight.start(task) else {
+            // unlocked by shutdown
+            return;
+        };
+        let (handler_option, auto_extend_lock) = {
+            let subscription_manager = self
+                .subscription_manager
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
+            let auto_extend_lock = subscription_manager
+                .get_settings(&task.topic_name)
+                .is_some_and(|settings| settings.auto_extend_lock);
+            (
+                subscription_manager.get_handler(&task.topic_name),
+                auto_extend_lock,
+            )
+        };
+        if !auto_extend_lock {
+            self.lock_extender.unregister(task);
+        }
+        if cancellation_token.reason() == Some(CancellationReason::LockLost) {
+            warn!("lost the lock of task {} while it was waiting", task.id);
+        } else if let Some(handler) = handler_option.or_else(|| self.handle_orphan(task)) {
+            let context = TaskContext::new(task, &self.engine_service, states, &cancellation_token);
+            // a panicking handler fails its task, but not the worker
+            let result = catch_panic(|| handler.handle(&context));
+            if cancellation_token.reason() == Some(CancellationReason::LockLost) {
+                warn!("lost the lock of task {}, outcome not reported", task.id);
+            } else if let Err(e) = self.engine_service.report_outcome(task, result) {
+                error!("failed to report outcome of task {}, {e:?}", task.id);
+            }
+        }
+        self.lock_extender.unregister(task);
+        self.in_flight.finish(task);
+    }
+
+    /// Applies the orphan policy to a task without handler, returns the fallback handler if
+    /// the task should be handled by it.
+    fn handle_orphan(&self, task: &Task) -> Option<Arc<dyn TaskHandler>> {
+        warn!(
+            "no handler for topic {} of task {}",
+            task.topic_name, task.id
+        );
+        match &self.orphan_policy {
+            OrphanPolicy::Unlock => {
+                trace!("unlock task {}", task.id);
+                if let Err(e) = self.engine_service.unlock(task) {
+                    error!("failed to unlock task {}, {e:?}", task.id);
+                }
+                None
+            }
+            OrphanPolicy::Failure => {
+                let message = format!("no handler for topic {}", task.topic_name);
+                let result = Err(HandlerError::new(&message));
+                if let Err(e) = self.engine_service.report_outcome(task, result) {
+                    error!("failed to report failure of task {}, {e:?}", task.id);
+                }
+                None
+            }
+            OrphanPolicy::Fallback(fallback) => Some(Arc::clone(&fallback.handler)),
+        }
+    }
+}
+
+const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Releases a fetched task which will not be executed.
+fn unlock(engine_service: &EngineService, lock_extender: &LockExtender, task: &Task) {
+    lock_extender.unregister(task);
+    trace!("unlock task {}", task.id);
+    if let Err(e) = engine_service.unlock(task) {
+        error!("failed to unlock task {}, {e:?}", task.id);
+    }
+}
+
+/// Fetched tasks which wait for their handler or are running, with their cancellation tokens.
+#[derive(Default)]
+struct InFlightTasks {
+    state: Mutex<InFlightState>,
+    idle: Condvar,
+}
+
+#[derive(Default)]
+struct InFlightState {
+    waiting: HashMap<String, (Task, CancellationToken)>,
+    running: HashMap<String, CancellationToken>,
+    closed: bool,
+}
+
+impl InFlightTasks {
+    fn reset(&self) {
+        self.state.lock().unwrap().closed = false;
+    }
+
+    /// Adds fetched tasks and returns their tokens by task id, unless the client is stopping.
+    fn add(&self, tasks: &[Task]) -> Option<HashMap<String, CancellationToken>> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return None;
+        }
+        let mut cancellation_tokens = HashMap::new();
+        for task in tasks {
+            let cancellation_token = CancellationToken::new();
+            cancellation_tokens.insert(task.id.to_owned(), cancellation_token.clone());
+            state
+                .waiting
+                .insert(task.id.to_owned(), (task.clone(), cancellation_token));
+        }
+        Some(cancellation_tokens)
+    }
+
+    /// Marks the handler of a task as running, unless the task was drained.
+    fn start(&self, task: &Task) -> Option<CancellationToken> {
+        let mut state = self.state.lock().unwrap();
+        let (_, cancellation_token) = state.waiting.remove(&task.id)?;
+        state
+            .running
+            .insert(task.id.to_owned(), cancellation_token.clone());
+        Some(cancellation_token)
+    }
+
+    fn finish(&self, task: &Task) {
+        let mut state = self.state.lock().unwrap();
+        state.running.remove(&task.id);
+        if state.running.is_empty() {
+            self.idle.notify_all();
+        }
+    }
+
+    fn cancel(&self, reason: CancellationReason) {
+        let state = self.state.lock().unwrap();
+        let waiting = state.waiting.values().map(|(_, token)| token);
+        waiting
+            .chain(state.running.values())
+            .for_each(|cancellation_token| cancellation_token.cancel(reason));
+    }
+
+    /// Stops accepting tasks.
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+    }
+
+    /// Stops accepting tasks and returns the ones whose handler has not started.
+    fn drain(&self) -> Vec<Task> {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        state.waiting.drain().map(|(_, (task, _))| task).collect()
+    }
+
+    /// Waits until no handler is running, returns false if `deadline` passed before.
+    fn wait_idle(&self, deadline: Instant) -> bool {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        let state = self.state.lock().unwrap();
+        let (state, _) = self
+            .idle
+            .wait_timeout_while(state, timeout, |state| !state.running.is_empty())
+            .unwrap();
+        state.running.is_empty()
+    }
+}
+
 struct TaskPolling {
     is_active: Arc<RwLock<bool>>,
-    interval: u64,
+    backoff_strategy: Arc<dyn BackoffStrategy>,
 }
 
 impl TaskPolling {
-    pub fn new(interval: u64) -> Self {
+    pub fn new(backoff_strategy: Arc<dyn BackoffStrategy>) -> Self {
         Self {
             is_active: Arc::new(RwLock::new(false)),
-            interval,
+            backoff_strategy,
         }
     }
 
     pub fn start<F>(&mut self, callback: F) -> Result<JoinHandle<()>>
     where
-        F: Fn() -> () + 'static + Send,
+        F: Fn() -> Result<usize> + 'static + Send,
     {
         if *self.is_active.read().unwrap() {
             return Err(Error::ClientError("client already started".to_string()));
@@ -157,22 +527,37 @@ impl TaskPolling {
     }
 
     pub fn is_active(&self) -> bool {
-        *self.is_active.write().unwrap()
+        *self.is_active.read().unwrap()
     }
 
     fn poll<F>(&self, callback: F) -> Result<JoinHandle<()>>
     where
-        F: Fn() -> () + 'static + Send,
+        F: Fn() -> Result<usize> + 'static + Send,
     {
         let is_active = Arc::clone(&self.is_active);
-        let millis = self.interval;
+        let backoff_strategy = Arc::clone(&self.backoff_strategy);
         let handler = thread::Builder::new()
             .name("client-internal-polling-runtime".into())
             .spawn(move || {
+                let _guard = InactiveOnDrop(Arc::clone(&is_active));
+                let mut poll_counter = PollCounter::default();
                 while *is_active.read().unwrap() {
                     trace!("polling...");
-                    callback();
-                    thread::sleep(Duration::from_millis(millis));
+                    let result = callback();
+                    let Some(delay) = poll_counter.next_delay(backoff_strategy.as_ref(), &result)
+                    else {
+                        continue;
+                    };
+                    trace!("next poll in {delay:?}");
+                    // parked, so that stopping the client wakes the thread up
+                    let wake_up_at = Instant::now() + delay;
+                    while *is_active.read().unwrap() {
+                        let now = Instant::now();
+                        if now >= wake_up_at {
+                            break;
+                        }
+                        thread::park_timeout(wake_up_at - now);
+                    }
                 }
             })
             .map_err(|e| {
@@ -183,3 +568,76 @@ impl TaskPolling {
         Ok(handler)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: &str) -> Task {
+        Task {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn runs_added_tasks() {
+        let in_flight = InFlightTasks::default();
+        let tokens = in_flight.add(&[task("a"), task("b")]).unwrap();
+
+        let token = in_flight.start(&task("a")).unwrap();
+        in_flight.cancel(CancellationReason::Stopped);
+
+        assert!(token.is_cancelled());
+        assert!(tokens["b"].is_cancelled());
+    }
+
+    #[test]
+    fn rejects_tasks_while_draining() {
+        let in_flight = InFlightTasks::default();
+        in_flight.drain();
+
+        assert!(in_flight.add(&[task("a")]).is_none());
+
+        in_flight.reset();
+        assert!(in_flight.add(&[task("a")]).is_some());
+    }
+
+    #[test]
+    fn rejects_tasks_after_stop() {
+        let in_flight = InFlightTasks::default();
+        in_flight.add(&[task("a")]).unwrap();
+
+        in_flight.close();
+
+        assert!(in_flight.add(&[task("b")]).is_none());
+        assert!(in_flight.start(&task("a")).is_some());
+    }
+
+    #[test]
+    fn drains_waiting_tasks() {
+        let in_flight = InFlightTasks::default();
+        in_flight.add(&[task("a"), task("b")]).unwrap();
+        in_flight.start(&task("a")).unwrap();
+
+        let drained = in_flight.drain();
+
+        assert_eq!(drained.len(), 1);
+        assert_eq!(drained[0].id, "b");
+        assert!(in_flight.start(&task("b")).is_none());
+    }
+
+    #[test]
+    fn waits_until_idle() {
+        let in_flight = Arc::new(InFlightTasks::default());
+        in_flight.add(&[task("a")]).unwrap();
+        in_flight.start(&task("a")).unwrap();
+
+        assert!(!in_flight.wait_idle(Instant::now() + Duration::from_millis(20)));
+
+        let finishing = Arc::clone(&in_flight);
+        let handle = thread::spawn(move || finishing.finish(&task("a")));
+        assert!(in_flight.wait_idle(Instant::now() + Duration::from_secs(5)));
+        handle.join().unwrap();
+    }
+}
@@ -1,3 +1,73 @@
+use std::sync::Arc;
+
+#[cfg(feature = "blocking")]
+use crate::client::extract::HandlerService;
+use crate::{BackoffStrategy, Error, Task};
+#[cfg(feature = "blocking")]
+use crate::{Handler, TaskHandler};
+
+/// Callback for errors of the polling loop, e.g. when the engine is not reachable.
+pub type ErrorHandler = Arc<dyn Fn(&Error) + Send + Sync>;
+
+/// Which fetched tasks may be handled at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutionStrategy {
+    /// All tasks run in parallel.
+    Parallel,
+    /// One task at a time. The blocking client runs them by priority, then by creation time.
+    Sequential,
+    /// Tasks of the same activity run one after the other, different activities in parallel.
+    #[default]
+    PerActivity,
+    /// Tasks of the same process instance run one after the other, so that a worker never
+    /// handles two tasks of one process instance at the same time.
+    PerProcessInstance,
+}
+
+impl ExecutionStrategy {
+    /// Tasks of the same group run one after the other.
+    pub(crate) fn group(&self, task: &Task) -> Option<String> {
+        match self {
+            ExecutionStrategy::Parallel => None,
+            ExecutionStrategy::Sequential => Some(String::new()),
+            ExecutionStrategy::PerActivity => Some(task.activity_id.to_owned()),
+            ExecutionStrategy::PerProcessInstance => Some(task.process_instance_id.to_owned()),
+        }
+    }
+}
+
+/// What the client does with a fetched task whose topic has no handler, e.g. after a race
+/// with a removed subscription. Each such task is logged.
+#[derive(Clone, Default)]
+pub enum OrphanPolicy {
+    /// Unlocks the task, so that it can be fetched again right away.
+    #[default]
+    Unlock,
+    /// Reports a failure of the task. Like for a failing handler, its retries are decremented,
+    /// or set to `Config::default_retries` if it has not failed before.
+    Failure,
+    /// Handles the task with a handler of the blocking client. The async client rejects it.
+    Fallback(FallbackHandler),
+}
+
+/// Handler of `OrphanPolicy::Fallback`, see `Config::with_fallback_handler`.
+#[derive(Clone)]
+pub struct FallbackHandler {
+    #[cfg(feature = "blocking")]
+    pub(crate) handler: Arc<dyn TaskHandler>,
+    #[cfg(not(feature = "blocking"))]
+    _private: (),
+}
+
+#[cfg(feature = "blocking")]
+impl FallbackHandler {
+    pub fn new<Args: 'static, T: Handler<Args>>(handler: T) -> Self {
+        Self {
+            handler: Arc::new(HandlerService::new(handler)),
+        }
+    }
+}
+
 pub struct Config {
     base_url: String,
     worker_id: String,
@@ -6,7 +76,15 @@ pub struct Config {
     interval: u64,
     lock_duration: u32,
     auto_poll: bool,
+    async_response_timeout: Option<u32>,
+    concurrency: usize,
+    execution_strategy: ExecutionStrategy,
+    orphan_policy: OrphanPolicy,
+    default_retries: u32,
+    default_retry_timeout: u64,
     interceptors: Vec<Interceptor>,
+    error_handler: Option<ErrorHandler>,
+    backoff_strategy: Option<Arc<dyn BackoffStrategy>>,
 }
 
 impl Default for Config {
@@ -19,16 +97,25 @@ impl Default for Config {
             interval: 1000,
             lock_duration: 50000,
             auto_poll: true,
+            async_response_timeout: None,
+            concurrency: 10,
+            execution_strategy: Default::default(),
+            orphan_policy: Default::default(),
+            default_retries: 0,
+            default_retry_timeout: 0,
             interceptors: vec![],
+            error_handler: None,
+            backoff_strategy: None,
         }
     }
 }
 
 impl Config {
     pub fn new(base_url: &str) -> Self {
-        let mut config = Self::default();
-        config.base_url = base_url.to_string();
-        config
+        Self {
+            base_url: base_url.to_string(),
+            ..Default::default()
+        }
     }
 
     pub fn base_url(&self) -> &str {
@@ -52,9 +139,33 @@ impl Config {
     pub fn auto_poll(&self) -> bool {
         self.auto_poll
     }
+    pub fn async_response_timeout(&self) -> Option<u32> {
+        self.async_response_timeout
+    }
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+    pub fn execution_strategy(&self) -> ExecutionStrategy {
+        self.execution_strategy
+    }
+    pub fn orphan_policy(&self) -> &OrphanPolicy {
+        &self.orphan_policy
+    }
+    pub fn default_retries(&self) -> u32 {
+        self.default_retries
+    }
+    pub fn default_retry_timeout(&self) -> u64 {
+        self.default_retry_timeout
+    }
     pub fn interceptors(&self) -> &Vec<Interceptor> {
         &self.interceptors
     }
+    pub fn error_handler(&self) -> Option<&ErrorHandler> {
+        self.error_handler.as_ref()
+    }
+    pub fn backoff_strategy(&self) -> Option<&Arc<dyn BackoffStrategy>> {
+        self.backoff_strategy.as_ref()
+    }
 
     pub fn with_base_url(mut self, base_url: String) -> Self {
         self.base_url = base_url;
@@ -72,6 +183,7 @@ impl Config {
         self.use_priority = use_priority;
         self
     }
+    /// Time to wait after a fetch without tasks or with an error, in milliseconds.
     pub fn with_interval(mut self, interval: u64) -> Self {
         self.interval = interval;
         self
@@ -84,10 +196,65 @@ impl Config {
         self.auto_poll = auto_poll;
         self
     }
+    /// Enables long polling: the engine holds a fetch request open for up to
+    /// `async_response_timeout` milliseconds until tasks are available (max. 1800000).
+    pub fn with_async_response_timeout(mut self, async_response_timeout: u32) -> Self {
+        self.async_response_timeout = Some(async_response_timeout);
+        self
+    }
+    /// Maximum number of tasks handled at the same time, the number of worker threads of the
+    /// blocking client.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    /// Tasks waiting for another task of their group keep their lock, it is extended until
+    /// their handler starts.
+    pub fn with_execution_strategy(mut self, execution_strategy: ExecutionStrategy) -> Self {
+        self.execution_strategy = execution_strategy;
+        self
+    }
+    pub fn with_orphan_policy(mut self, orphan_policy: OrphanPolicy) -> Self {
+        self.orphan_policy = orphan_policy;
+        self
+    }
+    /// Handles tasks of topics without handler with `handler`, see `OrphanPolicy::Fallback`.
+    #[cfg(feature = "blocking")]
+    pub fn with_fallback_handler<Args: 'static, T: Handler<Args>>(mut self, handler: T) -> Self {
+        self.orphan_policy = OrphanPolicy::Fallback(FallbackHandler::new(handler));
+        self
+    }
+    /// Retries left after the first failure of a task, unless the handler error sets them.
+    /// 0 by default, which creates an incident right away.
+    pub fn with_default_retries(mut self, default_retries: u32) -> Self {
+        self.default_retries = default_retries;
+        self
+    }
+    /// Time in milliseconds before a failed task can be fetched again, unless the handler
+    /// error sets it.
+    pub fn with_default_retry_timeout(mut self, default_retry_timeout: u64) -> Self {
+        self.default_retry_timeout = default_retry_timeout;
+        self
+    }
     pub fn with_interceptors(mut self, interceptors: Vec<Interceptor>) -> Self {
         self.interceptors = interceptors;
         self
     }
+    pub fn with_error_handler<F>(mut self, error_handler: F) -> Self
+    where
+        F: Fn(&Error) + Send + Sync + 'static,
+    {
+        self.error_handler = Some(Arc::new(error_handler));
+        self
+    }
+    /// Replaces the fixed `interval` between two fetches.
+    pub fn with_backoff_strategy<B>(mut self, backoff_strategy: B) -> Self
+    where
+        B: BackoffStrategy + 'static,
+    {
+        self.backoff_strategy = Some(Arc::new(backoff_strategy));
+        self
+    }
 }
 
 // TODO not implemented yet
@@ -0,0 +1,411 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use log::error;
+
+use crate::{Error, Result};
+
+/// Work item of the executor. Jobs with higher priority run first, older ones first for
+/// the same priority.
+pub(crate) struct Job {
+    pub priority: i64,
+    /// Creation time of the task in milliseconds since the epoch.
+    pub create_time: Option<i64>,
+    /// Jobs of the same group never run at the same time. They run in queue order, by
+    /// priority and creation time.
+    pub group: Option<String>,
+    pub topic: String,
+    /// Maximum number of jobs of the topic running at the same time.
+    pub topic_limit: Option<usize>,
+    pub run: Box<dyn FnOnce() + Send>,
+}
+
+/// Fixed size pool of worker threads executing the handlers of the blocking client.
+pub(crate) struct Executor {
+    shared: Arc<Shared>,
+    threads: usize,
+}
+
+struct Shared {
+    state: Mutex<ExecutorState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct ExecutorState {
+    queue: VecDeque<Job>,
+    running: usize,
+    running_groups: HashSet<String>,
+    running_topics: HashMap<String, usize>,
+    is_shutdown: bool,
+}
+
+impl Job {
+    fn runs_before(&self, other: &Job) -> bool {
+        match self.priority.cmp(&other.priority) {
+            Ordering::Greater => true,
+            Ordering::Less => false,
+            Ordering::Equal => match (&self.create_time, &other.create_time) {
+                (Some(create_time), Some(other_create_time)) => create_time < other_create_time,
+                _ => false,
+            },
+        }
+    }
+}
+
+impl ExecutorState {
+    /// Queues the job in front of the first job it runs before, so that equal jobs keep
+    /// their order.
+    fn push(&mut self, job: Job) {
+        let index = self
+            .queue
+            .iter()
+            .position(|queued| job.runs_before(queued))
+            .unwrap_or(self.queue.len());
+        self.queue.insert(index, job);
+    }
+
+    /// Takes the first job which is neither blocked by its group nor by its topic limit.
+    fn take_runnable(&mut self) -> Option<Job> {
+        let index = self.queue.iter().position(|job| {
+            let group_free = job
+                .group
+                .as_ref()
+                .is_none_or(|group| !self.running_groups.contains(group));
+            let topic_free = job.topic_limit.is_none_or(|limit| {
+                self.running_topics.get(&job.topic).copied().unwrap_or(0) < limit
+            });
+            group_free && topic_free
+        })?;
+        let job = self.queue.remove(index)?;
+        self.running += 1;
+        if let Some(group) = &job.group {
+            self.running_groups.insert(group.to_owned());
+        }
+        *self.running_topics.entry(job.topic.to_owned()).or_default() += 1;
+        Some(job)
+    }
+
+    fn topic_jobs(&self) -> HashMap<String, usize> {
+        let mut topic_jobs = self.running_topics.clone();
+        for job in &self.queue {
+            *topic_jobs.entry(job.topic.to_owned()).or_default() += 1;
+        }
+        topic_jobs
+    }
+
+    fn finished(&mut self, group: Option<String>, topic: &str) {
+        self.running -= 1;
+        if let Some(group) = group {
+            self.running_groups.remove(&group);
+        }
+        if let Some(running) = self.running_topics.get_mut(topic) {
+            *running -= 1;
+            if *running == 0 {
+                self.running_topics.remove(topic);
+            }
+        }
+    }
+}
+
+impl Executor {
+    pub fn new(threads: usize) -> Result<Self> {
+        let threads = threads.max(1);
+        let shared = Arc::new(Shared {
+            state: Mutex::new(ExecutorState::default()),
+            changed: Condvar::new(),
+        });
+        for i in 0..threads {
+            let shared = Arc::clone(&shared);
+            thread::Builder::new()
+                .name(format!("client-internal-worker-{i}"))
+                .spawn(move || shared.work())
+                .map_err(|e| {
+                    error!("{e:?}");
+                    Error::ClientError("failed to spawn worker thread".into())
+                })?;
+        }
+        Ok(Self { shared, threads })
+    }
+
+    pub fn submit(&self, job: Job) {
+        self.shared.lock().push(job);
+        self.shared.changed.notify_all();
+    }
+
+    /// Waits up to `timeout` for a worker without job, returns the number of free workers.
+    pub fn wait_for_capacity(&self, timeout: Duration) -> usize {
+        let state = self.shared.lock();
+        let (state, _) = self
+            .shared
+            .changed
+            .wait_timeout_while(state, timeout, |state| self.free_workers(state) == 0)
+            .unwrap_or_else(PoisonError::into_inner);
+        self.free_workers(&state)
+    }
+
+    /// Waits up to `timeout` for a job to be submitted or to finish.
+    pub fn wait_for_change(&self, timeout: Duration) {
+        let state = self.shared.lock();
+        let _ = self.shared.changed.wait_timeout(state, timeout);
+    }
+
+    /// Number of queued or running jobs per topic.
+    pub fn topic_jobs(&self) -> HashMap<String, usize> {
+        self.shared.lock().topic_jobs()
+    }
+
+    fn free_workers(&self, state: &ExecutorState) -> usize {
+        self.threads
+            .saturating_sub(state.running + state.queue.len())
+    }
+}
+
+impl Drop for Executor {
+    fn drop(&mut self) {
+        self.shared.lock().is_shutdown = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, ExecutorState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn work(&self) {
+        let mut state = self.lock();
+        loop {
+            if state.is_shutdown {
+                return;
+            }
+            let Some(job) = state.take_runnable() else {
+                state = self
+                    .changed
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+                continue;
+            };
+            drop(state);
+
+            let Job {
+                group, topic, run, ..
+            } = job;
+            run();
+
+            state = self.lock();
+            state.finished(group, &topic);
+            self.changed.notify_all();
+        }
+    }
+}
+
+/// Parses a date of the engine, `yyyy-MM-dd'T'HH:mm:ss.SSSZ` like
+/// `2024-03-31T10:15:00.000+0200`, into milliseconds since the epoch.
+pub(crate) fn timestamp_millis(date_time: &str) -> Option<i64> {
+    let (date, time) = date_time.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+
+    let (time, offset_minutes) = match time.strip_suffix('Z') {
+        Some(time) => (time, 0),
+        None => {
+            let (time, offset) = time.split_at(time.rfind(['+', '-'])?);
+            let digits = offset[1..].replace(':', "");
+            if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let minutes =
+                digits[..2].parse::<i64>().ok()? * 60 + digits[2..].parse::<i64>().ok()?;
+            (
+                time,
+                if offset.starts_with('-') {
+                    -minutes
+                } else {
+                    minutes
+                },
+            )
+        }
+    };
+    let (time, millis) = match time.split_once('.') {
+        Some((time, fraction)) => {
+            let fraction = format!("{fraction:0<3}");
+            (time, fraction.get(..3)?.parse::<i64>().ok()?)
+        }
+        None => (time, 0),
+    };
+    let mut time = time.splitn(3, ':').map(str::parse::<i64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+
+    let valid = (1..=12).contains(&month)
+        && (1..=31).contains(&day)
+        && (0..24).contains(&hour)
+        && (0..60).contains(&minute)
+        && (0..=60).contains(&second);
+    if !valid {
+        return None;
+    }
+    let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3_600 + minute * 60 + second
+        - offset_minutes * 60;
+    Some(seconds * 1_000 + millis)
+}
+
+/// Days since 1970-01-01 of a date of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    fn job(group: Option<&str>, topic_limit: Option<usize>) -> Job {
+        Job {
+            priority: 0,
+            create_time: None,
+            group: group.map(String::from),
+            topic: "topic".into(),
+            topic_limit,
+            run: Box::new(|| ()),
+        }
+    }
+
+    fn queued(jobs: Vec<Job>) -> ExecutorState {
+        ExecutorState {
+            queue: jobs.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn counts_queued_and_running_jobs_per_topic() {
+        let mut state = queued(vec![job(None, Some(2)), job(None, Some(2))]);
+        state.take_runnable().unwrap();
+
+        assert_eq!(
+            state.topic_jobs(),
+            HashMap::from([("topic".to_string(), 2)])
+        );
+    }
+
+    #[test]
+    fn blocks_jobs_of_running_group() {
+        let mut state = queued(vec![
+            job(Some("g"), None),
+            job(Some("g"), None),
+            job(Some("h"), None),
+        ]);
+
+        let first = state.take_runnable().unwrap();
+        let second = state.take_runnable().unwrap();
+        assert_eq!(second.group.as_deref(), Some("h"));
+        assert!(state.take_runnable().is_none());
+
+        state.finished(first.group, &first.topic);
+        assert_eq!(state.take_runnable().unwrap().group.as_deref(), Some("g"));
+        assert_eq!(state.running, 2);
+    }
+
+    #[test]
+    fn limits_running_jobs_of_topic() {
+        let mut state = queued(vec![
+            job(None, Some(2)),
+            job(None, Some(2)),
+            job(None, Some(2)),
+        ]);
+
+        let first = state.take_runnable().unwrap();
+        assert!(state.take_runnable().is_some());
+        assert!(state.take_runnable().is_none());
+
+        state.finished(first.group, &first.topic);
+        assert!(state.take_runnable().is_some());
+        assert!(state.queue.is_empty());
+    }
+
+    #[test]
+    fn runs_submitted_jobs_on_workers() {
+        let executor = Executor::new(2).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        for i in 0..4 {
+            let sender = sender.clone();
+            executor.submit(Job {
+                run: Box::new(move || sender.send(i).unwrap()),
+                ..job(None, None)
+            });
+        }
+
+        let mut done: Vec<i32> = receiver.iter().take(4).collect();
+        done.sort();
+        assert_eq!(done, vec![0, 1, 2, 3]);
+        assert_eq!(executor.wait_for_capacity(Duration::from_secs(10)), 2);
+    }
+
+    fn prioritized(priority: i64, create_time: Option<&str>) -> Job {
+        Job {
+            priority,
+            create_time: create_time.and_then(timestamp_millis),
+            ..job(None, None)
+        }
+    }
+
+    #[test]
+    fn queues_jobs_by_priority_and_creation_time() {
+        let mut state = ExecutorState::default();
+        state.push(prioritized(0, Some("2024-01-01T12:00:00.000+0000")));
+        state.push(prioritized(5, None));
+        // an hour earlier than the first job in UTC
+        state.push(prioritized(0, Some("2024-01-01T12:00:00.000+0100")));
+        state.push(prioritized(5, None));
+        state.push(prioritized(0, None));
+
+        let order: Vec<_> = state
+            .queue
+            .iter()
+            .map(|job| (job.priority, job.create_time))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                (5, None),
+                (5, None),
+                (0, Some(1_704_106_800_000)),
+                (0, Some(1_704_110_400_000)),
+                (0, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_engine_dates() {
+        assert_eq!(timestamp_millis("1970-01-01T00:00:00.000+0000"), Some(0));
+        assert_eq!(
+            timestamp_millis("2024-02-29T23:59:59.999+0000"),
+            Some(1_709_251_199_999)
+        );
+        assert_eq!(
+            timestamp_millis("2024-03-01T01:30:00.5+02:00"),
+            Some(1_709_249_400_500)
+        );
+        assert_eq!(timestamp_millis("1969-12-31T19:00:00Z"), Some(-18_000_000));
+        assert_eq!(
+            timestamp_millis("2024-01-01T00:00:00-0130"),
+            Some(1_704_072_600_000)
+        );
+        assert_eq!(timestamp_millis("2024-13-01T00:00:00.000+0000"), None);
+        assert_eq!(timestamp_millis("2024-01-01"), None);
+        assert_eq!(timestamp_millis("2024-01-01T00:00:00.000"), None);
+        assert_eq!(timestamp_millis("2024-01-01T00:00:00.000+1é2"), None);
+        assert_eq!(timestamp_millis("2024-01-01T00:00:00.000+-100"), None);
+    }
+}
@@ -0,0 +1,316 @@
+//! Extraction of handler arguments from the fetched task.
+//!
+//! ```no_run
+//! use serde::Deserialize;
+//! use camunda_external_task_client_rust::*;
+//!
+//! #[derive(Deserialize)]
+//! struct LoanRequest {
+//!     amount: i64,
+//! }
+//!
+//! struct Db;
+//!
+//! fn handle(
+//!     Vars(request): Vars<LoanRequest>,
+//!     business_key: Option<BusinessKey>,
+//!     engine_service: &EngineService,
+//!     State(db): State<Db>,
+//! ) -> HandlerResult {
+//!     // your code
+//!     Ok(TaskOutcome::complete())
+//! }
+//!
+//! # fn main() -> Result<()> {
+//! let client = Client::new(Config::new("http://..."))?.with_state(Db);
+//! client.subscribe("loanGranter").handler(handle).open()?;
+//! # Ok(())
+//! # }
+//! ```
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+
+use crate::client::state::StateMap;
+use crate::{CancellationToken, EngineService, HandlerError, HandlerResult, Task, TaskHandler};
+
+/// Everything a handler can extract its arguments from.
+pub struct TaskContext<'a> {
+    task: &'a Task,
+    engine_service: &'a EngineService,
+    states: &'a StateMap,
+    cancellation_token: &'a CancellationToken,
+}
+
+impl<'a> TaskContext<'a> {
+    pub(crate) fn new(
+        task: &'a Task,
+        engine_service: &'a EngineService,
+        states: &'a StateMap,
+        cancellation_token: &'a CancellationToken,
+    ) -> Self {
+        Self {
+            task,
+            engine_service,
+            states,
+            cancellation_token,
+        }
+    }
+
+    pub fn task(&self) -> &'a Task {
+        self.task
+    }
+
+    pub fn engine_service(&self) -> &'a EngineService {
+        self.engine_service
+    }
+
+    /// Cancelled when the client stops or the lock of the task is lost.
+    pub fn cancellation_token(&self) -> &'a CancellationToken {
+        self.cancellation_token
+    }
+
+    /// State of type `S` added with `Client::with_state`.
+    pub fn state<S: Send + Sync + 'static>(&self) -> Option<Arc<S>> {
+        self.states.get()
+    }
+}
+
+/// Argument of a handler function, extracted from the `TaskContext`.
+///
+/// If extraction fails, the handler is not called and the error is reported as a failure of
+/// the task.
+pub trait FromTask {
+    /// The extracted argument, which may borrow from the context.
+    type Output<'a>;
+
+    fn from_task<'a>(context: &'a TaskContext<'_>) -> Result<Self::Output<'a>, HandlerError>;
+}
+
+impl FromTask for &Task {
+    type Output<'a> = &'a Task;
+
+    fn from_task<'a>(context: &'a TaskContext<'_>) -> Result<Self::Output<'a>, HandlerError> {
+        Ok(context.task)
+    }
+}
+
+impl FromTask for &EngineService {
+    type Output<'a> = &'a EngineService;
+
+    fn from_task<'a>(context: &'a TaskContext<'_>) -> Result<Self::Output<'a>, HandlerError> {
+        Ok(context.engine_service)
+    }
+}
+
+impl FromTask for &TaskContext<'_> {
+    type Output<'a> = &'a TaskContext<'a>;
+
+    fn from_task<'a>(context: &'a TaskContext<'_>) -> Result<Self::Output<'a>, HandlerError> {
+        Ok(context)
+    }
+}
+
+impl FromTask for CancellationToken {
+    type Output<'a> = CancellationToken;
+
+    fn from_task<'a>(context: &'a TaskContext<'_>) -> Result<Self::Output<'a>, HandlerError> {
+        Ok(context.cancellation_token.clone())
+    }
+}
+
+/// Variables of the task, deserialized with `Task::variables_as`.
+pub struct Vars<T>(pub T);
+
+impl<T: DeserializeOwned> FromTask for Vars<T> {
+    type Output<'a> = Vars<T>;
+
+    fn from_task<'a>(context: &'a TaskContext<'_>) -> Result<Self::Output<'a>, HandlerError> {
+        Ok(Vars(context.task.variables_as()?))
+    }
+}
+
+/// Business key of the process instance, extraction fails if it has none.
+pub struct BusinessKey(pub String);
+
+impl FromTask for BusinessKey {
+    type Output<'a> = BusinessKey;
+
+    fn from_task<'a>(context: &'a TaskContext<'_>) -> Result<Self::Output<'a>, HandlerError> {
+        context
+            .task
+            .business_key
+            .clone()
+            .map(BusinessKey)
+            .ok_or_else(|| HandlerError::new("task has no business key"))
+    }
+}
+
+/// Makes any argument optional, it is `None` if its extraction fails.
+impl<T: FromTask> FromTask for Option<T> {
+    type Output<'a> = Option<T::Output<'a>>;
+
+    fn from_task<'a>(context: &'a TaskContext<'_>) -> Result<Self::Output<'a>, HandlerError> {
+        Ok(T::from_task(context).ok())
+    }
+}
+
+/// Functions which can be subscribed as handler, with `Args` being the tuple of their
+/// argument types.
+///
+/// Implemented for functions and closures whose arguments all implement `FromTask` and which
+/// return a `HandlerResult`, e.g. `Fn(Vars<LoanRequest>, BusinessKey, &EngineService)`, and
+/// for every `TaskHandler`.
+pub trait Handler<Args>: Send + Sync + 'static {
+    fn call(&self, context: &TaskContext) -> HandlerResult;
+}
+
+#[doc(hidden)]
+pub struct TaskHandlerMarker;
+
+impl<T: TaskHandler + 'static> Handler<TaskHandlerMarker> for T {
+    fn call(&self, context: &TaskContext) -> HandlerResult {
+        self.handle(context)
+    }
+}
+
+macro_rules! impl_handler {
+    ($($arg:ident),*) => {
+        #[allow(non_snake_case, unused_variables)]
+        impl<F, $($arg: FromTask),*> Handler<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> HandlerResult
+                + Fn($($arg::Output<'_>),*) -> HandlerResult
+                + Send
+                + Sync
+                + 'static,
+        {
+            fn call(&self, context: &TaskContext) -> HandlerResult {
+                $(let $arg = $arg::from_task(context)?;)*
+                self($($arg),*)
+            }
+        }
+    };
+}
+
+impl_handler!();
+impl_handler!(A);
+impl_handler!(A, B);
+impl_handler!(A, B, C);
+impl_handler!(A, B, C, D);
+impl_handler!(A, B, C, D, E);
+impl_handler!(A, B, C, D, E, G);
+impl_handler!(A, B, C, D, E, G, H);
+impl_handler!(A, B, C, D, E, G, H, I);
+
+/// Adapts a `Handler` to the `TaskHandler` stored by the subscription.
+pub(crate) struct HandlerService<T, Args> {
+    handler: T,
+    _args: PhantomData<fn() -> Args>,
+}
+
+impl<T, Args> HandlerService<T, Args> {
+    pub(crate) fn new(handler: T) -> Self {
+        Self {
+            handler,
+            _args: PhantomData,
+        }
+    }
+}
+
+impl<T: Handler<Args>, Args> TaskHandler for HandlerService<T, Args> {
+    fn handle(&self, context: &TaskContext) -> HandlerResult {
+        self.handler.call(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::client::State;
+    use crate::{Config, Variable};
+
+    #[derive(Deserialize)]
+    struct LoanRequest {
+        #[allow(dead_code)]
+        amount: i64,
+    }
+
+    fn extract<T: FromTask>(
+        task: &Task,
+        states: &StateMap,
+        check: impl FnOnce(Result<T::Output<'_>, HandlerError>),
+    ) {
+        let engine_service = EngineService::new(Arc::new(Config::new("http://localhost"))).unwrap();
+        let token = CancellationToken::new();
+        let context = TaskContext::new(task, &engine_service, states, &token);
+        check(T::from_task(&context));
+    }
+
+    fn error_message<T>(result: Result<T, HandlerError>) -> String {
+        match result {
+            Ok(_) => panic!("expected extraction to fail"),
+            Err(e) => e.message,
+        }
+    }
+
+    #[test]
+    fn extracts_business_key() {
+        let task = Task {
+            business_key: Some("loan-1".into()),
+            ..Default::default()
+        };
+
+        extract::<BusinessKey>(&task, &StateMap::default(), |result| {
+            assert_eq!(result.unwrap().0, "loan-1");
+        });
+    }
+
+    #[test]
+    fn fails_without_business_key() {
+        extract::<BusinessKey>(&Task::default(), &StateMap::default(), |result| {
+            assert_eq!(error_message(result), "task has no business key");
+        });
+    }
+
+    #[test]
+    fn option_turns_error_into_none() {
+        extract::<Option<BusinessKey>>(&Task::default(), &StateMap::default(), |result| {
+            assert!(result.unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn extracts_state() {
+        let mut states = StateMap::default();
+        states.insert(42_u32);
+
+        extract::<State<u32>>(&Task::default(), &states, |result| {
+            assert_eq!(*result.unwrap(), 42);
+        });
+    }
+
+    #[test]
+    fn fails_without_state() {
+        extract::<State<u32>>(&Task::default(), &StateMap::default(), |result| {
+            assert_eq!(error_message(result), "no state of type u32 added");
+        });
+    }
+
+    #[test]
+    fn vars_names_failing_variable() {
+        let task = Task {
+            variables: HashMap::from([("amount".into(), Variable::string("a lot"))]),
+            ..Default::default()
+        };
+
+        extract::<Vars<LoanRequest>>(&task, &StateMap::default(), |result| {
+            assert!(error_message(result).contains(r#"name: Some("amount")"#));
+        });
+    }
+}
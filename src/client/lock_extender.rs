@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use log::{debug, trace, warn};
+
+use crate::{CancellationReason, CancellationToken, Error, Result, Task};
+
+/// Time between two checks for locks which need to be extended.
+pub(crate) const TICK: Duration = Duration::from_millis(250);
+
+struct LockedTask {
+    task: Task,
+    lock_duration: u32,
+    next_extension: Instant,
+    lock_expiry: Instant,
+    cancellation_token: CancellationToken,
+}
+
+/// Registry of in-flight tasks whose locks are extended periodically by a background thread
+/// or task of the client.
+pub(crate) struct LockExtender {
+    tasks: Mutex<HashMap<String, LockedTask>>,
+    is_active: RwLock<bool>,
+}
+
+impl LockExtender {
+    pub fn new() -> Self {
+        Self {
+            tasks: Mutex::new(HashMap::new()),
+            is_active: RwLock::new(false),
+        }
+    }
+
+    /// Registers a task that was locked at `locked_at` for `lock_duration` milliseconds. The
+    /// token is cancelled if the lock cannot be extended.
+    pub fn register(
+        &self,
+        task: &Task,
+        lock_duration: u32,
+        locked_at: Instant,
+        cancellation_token: &CancellationToken,
+    ) {
+        trace!("register task {} for lock extension", task.id);
+        let locked_task = LockedTask {
+            task: task.clone(),
+            lock_duration,
+            next_extension: locked_at + half(lock_duration),
+            lock_expiry: locked_at + Duration::from_millis(lock_duration.into()),
+            cancellation_token: cancellation_token.clone(),
+        };
+        self.tasks
+            .lock()
+            .unwrap()
+            .insert(task.id.to_owned(), locked_task);
+    }
+
+    pub fn unregister(&self, task: &Task) {
+        self.tasks.lock().unwrap().remove(&task.id);
+    }
+
+    pub fn activate(&self) -> Result<()> {
+        let mut is_active = self.is_active.write().unwrap();
+        if *is_active {
+            return Err(Error::ClientError(
+                "lock extender already started".to_string(),
+            ));
+        }
+        *is_active = true;
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        *self.is_active.write().unwrap() = false;
+    }
+
+    pub fn is_active(&self) -> bool {
+        *self.is_active.read().unwrap()
+    }
+
+    /// Returns the tasks whose locks are due to be extended, with their lock durations.
+    pub fn due_tasks(&self) -> Vec<(Task, u32)> {
+        let now = Instant::now();
+        self.tasks
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|locked_task| locked_task.next_extension <= now)
+            .map(|locked_task| (locked_task.task.clone(), locked_task.lock_duration))
+            .collect()
+    }
+
+    /// Records the result of extending the lock of a task at `extended_at`. Transport and
+    /// server errors are retried on the next tick until the lock expires.
+    pub fn extended(
+        &self,
+        task: &Task,
+        lock_duration: u32,
+        extended_at: Instant,
+        result: Result<()>,
+    ) {
+        match result {
+            Ok(_) => {
+                debug!("extended lock of task {} by {lock_duration}ms", task.id);
+                if let Some(locked_task) = self.tasks.lock().unwrap().get_mut(&task.id) {
+                    locked_task.next_extension = extended_at + half(lock_duration);
+                    locked_task.lock_expiry =
+                        extended_at + Duration::from_millis(lock_duration.into());
+                }
+            }
+            Err(e) => {
+                warn!("failed to extend lock of task {}, {e:?}", task.id);
+                let mut tasks = self.tasks.lock().unwrap();
+                // the engine rejects the extension if the task is gone or locked by another worker
+                let rejected = matches!(&e, Error::EngineError(e) if e.is_client_error());
+                let expired = tasks
+                    .get(&task.id)
+                    .is_some_and(|locked_task| locked_task.lock_expiry <= Instant::now());
+                if !rejected && !expired {
+                    return;
+                }
+                if let Some(locked_task) = tasks.remove(&task.id) {
+                    warn!("lost lock of task {}", task.id);
+                    locked_task
+                        .cancellation_token
+                        .cancel(CancellationReason::LockLost);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+mod blocking {
+    use std::sync::Arc;
+    use std::thread;
+    use std::thread::JoinHandle;
+    use std::time::Instant;
+
+    use log::error;
+
+    use super::{LockExtender, TICK};
+    use crate::{EngineService, Error, Result};
+
+    impl LockExtender {
+        pub fn start(
+            self: &Arc<Self>,
+            engine_service: Arc<EngineService>,
+        ) -> Result<JoinHandle<()>> {
+            self.activate()?;
+            let lock_extender = Arc::clone(self);
+            thread::Builder::new()
+                .name("client-internal-lock-extender".into())
+                .spawn(move || {
+                    while lock_extender.is_active() {
+                        for (task, lock_duration) in lock_extender.due_tasks() {
+                            let extended_at = Instant::now();
+                            let result = engine_service.extend_lock(&task, lock_duration.into());
+                            lock_extender.extended(&task, lock_duration, extended_at, result);
+                        }
+                        thread::sleep(TICK);
+                    }
+                })
+                .map_err(|e| {
+                    error!("{e:?}");
+                    Error::ClientError("failed to spawn lock extender thread".into())
+                })
+        }
+    }
+}
+
+fn half(lock_duration: u32) -> Duration {
+    Duration::from_millis((lock_duration / 2).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: &str) -> Task {
+        Task {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    fn error() -> Result<()> {
+        Err(Error::BackendRequestError("connection refused".into()))
+    }
+
+    #[test]
+    fn retries_failed_extension_while_locked() {
+        let lock_extender = LockExtender::new();
+        let token = CancellationToken::new();
+        let locked_at = Instant::now() - Duration::from_millis(600);
+        lock_extender.register(&task("a"), 1000, locked_at, &token);
+
+        assert_eq!(lock_extender.due_tasks().len(), 1);
+        lock_extender.extended(&task("a"), 1000, Instant::now(), error());
+
+        assert!(!token.is_cancelled());
+        assert_eq!(lock_extender.due_tasks().len(), 1);
+    }
+
+    #[test]
+    fn cancels_when_lock_expired() {
+        let lock_extender = LockExtender::new();
+        let token = CancellationToken::new();
+        let locked_at = Instant::now() - Duration::from_millis(1000);
+        lock_extender.register(&task("a"), 1000, locked_at, &token);
+
+        lock_extender.extended(&task("a"), 1000, Instant::now(), error());
+
+        assert_eq!(token.reason(), Some(CancellationReason::LockLost));
+        assert!(lock_extender.due_tasks().is_empty());
+    }
+
+    #[test]
+    fn successful_extension_moves_expiry() {
+        let lock_extender = LockExtender::new();
+        let token = CancellationToken::new();
+        let locked_at = Instant::now() - Duration::from_millis(1000);
+        lock_extender.register(&task("a"), 1000, locked_at, &token);
+
+        lock_extender.extended(&task("a"), 1000, Instant::now(), Ok(()));
+        assert!(lock_extender.due_tasks().is_empty());
+        lock_extender.extended(&task("a"), 1000, Instant::now(), error());
+
+        assert!(!token.is_cancelled());
+    }
+}
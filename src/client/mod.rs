@@ -1,11 +1,54 @@
+pub use backoff::*;
+pub use cancellation::*;
+#[cfg(feature = "blocking")]
 pub use client::*;
 pub use config::*;
+#[cfg(feature = "blocking")]
+pub use extract::*;
+pub use outcome::*;
+#[cfg(feature = "blocking")]
+pub use state::*;
 pub use subscription::*;
 
-use crate::{EngineService, Task};
+use std::sync::{Arc, RwLock};
 
+pub(crate) mod backoff;
+mod cancellation;
+#[cfg(feature = "blocking")]
+#[allow(clippy::module_inception)]
 mod client;
 mod config;
+#[cfg(feature = "blocking")]
+mod executor;
+#[cfg(feature = "blocking")]
+pub(crate) mod extract;
+pub(crate) mod lock_extender;
+pub(crate) mod outcome;
+pub(crate) mod panic;
+#[cfg(feature = "blocking")]
+pub(crate) mod state;
 mod subscription;
 
-pub type TaskHandler = fn(&Task, &EngineService) -> ();
+/// Handles the tasks fetched for a subscribed topic.
+///
+/// Subscriptions accept any `Handler`, usually a function or closure taking extractors
+/// like `&Task`, `&EngineService` or `Vars<T>`. Implement this trait for a struct when the
+/// handler needs more structure.
+///
+/// The returned outcome is reported to the engine by the client.
+#[cfg(feature = "blocking")]
+pub trait TaskHandler: Send + Sync {
+    fn handle(&self, context: &TaskContext) -> HandlerResult;
+}
+
+/// Marks polling as inactive when dropped at the end of the polling loop, even by a panic.
+pub(crate) struct InactiveOnDrop(pub Arc<RwLock<bool>>);
+
+impl Drop for InactiveOnDrop {
+    fn drop(&mut self) {
+        match self.0.write() {
+            Ok(mut is_active) => *is_active = false,
+            Err(poisoned) => *poisoned.into_inner() = false,
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use log::{error, trace};
+
+use crate::service::request::EngineRequest;
+use crate::{Config, ProcessVariables, Task, Variable};
+
+pub type HandlerResult = std::result::Result<TaskOutcome, HandlerError>;
+
+/// Result of a handler, reported to the engine by the client.
+pub enum TaskOutcome {
+    Complete {
+        variables: Option<HashMap<String, Variable>>,
+        local_variables: Option<HashMap<String, Variable>>,
+    },
+    BpmnError {
+        code: String,
+        message: Option<String>,
+        variables: Option<HashMap<String, Variable>>,
+    },
+    Failure {
+        message: String,
+        details: Option<String>,
+        retries: u32,
+        retry_timeout: u64,
+        variables: Option<HashMap<String, Variable>>,
+        local_variables: Option<HashMap<String, Variable>>,
+    },
+    Unlock,
+}
+
+impl TaskOutcome {
+    /// Completes the task without setting any variables.
+    pub fn complete() -> Self {
+        Self::Complete {
+            variables: None,
+            local_variables: None,
+        }
+    }
+
+    pub fn complete_with(variables: HashMap<String, Variable>) -> Self {
+        Self::Complete {
+            variables: Some(variables),
+            local_variables: None,
+        }
+    }
+
+    /// Completes the task with the variables and local variables of `output`.
+    pub fn complete_from<T: ProcessVariables>(output: &T) -> crate::Result<Self> {
+        Ok(Self::Complete {
+            variables: Some(output.to_variables()?),
+            local_variables: Some(output.to_local_variables()?),
+        })
+    }
+
+    pub fn bpmn_error(code: &str) -> Self {
+        Self::BpmnError {
+            code: code.to_string(),
+            message: None,
+            variables: None,
+        }
+    }
+}
+
+/// Error returned by a handler, reported to the engine as a failure of the task.
+///
+/// Any error type converts into it, so `?` can be used inside of handlers. If `retries`
+/// is not set, the retries of the task are decremented, or set to `Config::default_retries`
+/// if the task has not failed before. `retry_timeout` defaults to
+/// `Config::default_retry_timeout`.
+#[derive(Debug)]
+pub struct HandlerError {
+    pub message: String,
+    pub details: Option<String>,
+    pub retries: Option<u32>,
+    pub retry_timeout: Option<u64>,
+}
+
+impl HandlerError {
+    pub fn new(message: &str) -> Self {
+        Self {
+            message: message.to_string(),
+            details: None,
+            retries: None,
+            retry_timeout: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: &str) -> Self {
+        self.details = Some(details.to_string());
+        self
+    }
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = Some(retries);
+        self
+    }
+    pub fn with_retry_timeout(mut self, retry_timeout: u64) -> Self {
+        self.retry_timeout = Some(retry_timeout);
+        self
+    }
+}
+
+impl<E: std::error::Error> From<E> for HandlerError {
+    fn from(e: E) -> Self {
+        Self::new(&e.to_string()).with_details(&format!("{e:?}"))
+    }
+}
+
+impl Display for HandlerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Turns the error of a handler into a failure of the task.
+pub(crate) fn into_outcome(config: &Config, task: &Task, result: HandlerResult) -> TaskOutcome {
+    result.unwrap_or_else(|e| {
+        error!("handler failed for task {}, {e:?}", task.id);
+        TaskOutcome::Failure {
+            retries: e.retries.unwrap_or_else(|| {
+                task.retries
+                    .map_or(config.default_retries(), |r| r.saturating_sub(1))
+            }),
+            message: e.message,
+            details: e.details,
+            retry_timeout: e
+                .retry_timeout
+                .unwrap_or_else(|| config.default_retry_timeout()),
+            variables: None,
+            local_variables: None,
+        }
+    })
+}
+
+/// Builds the engine call matching the result of a handler.
+pub(crate) fn outcome_request(
+    config: &Config,
+    task: &Task,
+    result: HandlerResult,
+) -> crate::Result<EngineRequest> {
+    match into_outcome(config, task, result) {
+        TaskOutcome::Complete {
+            variables,
+            local_variables,
+        } => {
+            trace!("complete task {}", task.id);
+            EngineRequest::complete(config, task, variables, local_variables)
+        }
+        TaskOutcome::BpmnError {
+            code,
+            message,
+            variables,
+        } => {
+            trace!("report bpmn error {code} for task {}", task.id);
+            EngineRequest::handle_bpmn_error(config, task, &code, message.as_deref(), variables)
+        }
+        TaskOutcome::Failure {
+            message,
+            details,
+            retries,
+            retry_timeout,
+            variables,
+            local_variables,
+        } => {
+            trace!("report failure for task {}", task.id);
+            EngineRequest::handle_failure(
+                config,
+                task,
+                &message,
+                details.as_deref(),
+                retries,
+                retry_timeout,
+                variables,
+                local_variables,
+            )
+        }
+        TaskOutcome::Unlock => {
+            trace!("unlock task {}", task.id);
+            Ok(EngineRequest::unlock(task))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(retries: Option<u32>) -> Task {
+        Task {
+            id: "task".into(),
+            retries,
+            ..Default::default()
+        }
+    }
+
+    fn failure(outcome: TaskOutcome) -> (String, u32, u64) {
+        match outcome {
+            TaskOutcome::Failure {
+                message,
+                retries,
+                retry_timeout,
+                ..
+            } => (message, retries, retry_timeout),
+            _ => panic!("expected a failure"),
+        }
+    }
+
+    #[test]
+    fn keeps_successful_outcome() {
+        let outcome = into_outcome(&Config::default(), &task(None), Ok(TaskOutcome::Unlock));
+
+        assert!(matches!(outcome, TaskOutcome::Unlock));
+    }
+
+    #[test]
+    fn uses_defaults_for_first_failure() {
+        let config = Config::default()
+            .with_default_retries(3)
+            .with_default_retry_timeout(5000);
+
+        let outcome = into_outcome(&config, &task(None), Err(HandlerError::new("failed")));
+
+        assert_eq!(failure(outcome), ("failed".to_string(), 3, 5000));
+    }
+
+    #[test]
+    fn creates_incident_for_first_failure_by_default() {
+        let outcome = into_outcome(
+            &Config::default(),
+            &task(None),
+            Err(HandlerError::new("failed")),
+        );
+
+        assert_eq!(failure(outcome), ("failed".to_string(), 0, 0));
+    }
+
+    #[test]
+    fn decrements_retries_of_failed_task() {
+        let config = Config::default().with_default_retries(3);
+
+        let outcome = into_outcome(&config, &task(Some(2)), Err(HandlerError::new("failed")));
+        assert_eq!(failure(outcome).1, 1);
+
+        let outcome = into_outcome(&config, &task(Some(0)), Err(HandlerError::new("failed")));
+        assert_eq!(failure(outcome).1, 0);
+    }
+
+    #[test]
+    fn prefers_retries_of_handler_error() {
+        let config = Config::default()
+            .with_default_retries(3)
+            .with_default_retry_timeout(5000);
+        let error = HandlerError::new("failed")
+            .with_retries(7)
+            .with_retry_timeout(100);
+
+        let outcome = into_outcome(&config, &task(Some(2)), Err(error));
+
+        assert_eq!(failure(outcome), ("failed".to_string(), 7, 100));
+    }
+}
@@ -0,0 +1,130 @@
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::sync::Once;
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+
+#[cfg(feature = "async")]
+use crate::HandlerFuture;
+use crate::{HandlerError, HandlerResult};
+
+thread_local! {
+    static CATCHING: Cell<bool> = const { Cell::new(false) };
+    static BACKTRACE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+static INSTALL_HOOK: Once = Once::new();
+
+/// Chains a panic hook which captures the backtrace of panics inside of `catch_panic`.
+fn install_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if CATCHING.with(Cell::get) {
+                let backtrace = Backtrace::force_capture().to_string();
+                BACKTRACE.with(|cell| *cell.borrow_mut() = Some(backtrace));
+            }
+            previous_hook(info);
+        }));
+    });
+}
+
+/// Runs a handler, turning a panic into a `HandlerError` with the panic message and
+/// backtrace as details.
+#[cfg(feature = "blocking")]
+pub(crate) fn catch_panic<F: FnOnce() -> HandlerResult>(handler: F) -> HandlerResult {
+    catch(handler).unwrap_or_else(Err)
+}
+
+/// Runs an async handler, turning a panic while creating or polling its future into a
+/// `HandlerError` like `catch_panic`.
+#[cfg(feature = "async")]
+pub(crate) async fn catch_panic_async<F: FnOnce() -> HandlerFuture>(handler: F) -> HandlerResult {
+    match catch(handler) {
+        Ok(future) => CatchPanic(future).await,
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(feature = "async")]
+struct CatchPanic(HandlerFuture);
+
+#[cfg(feature = "async")]
+impl Future for CatchPanic {
+    type Output = HandlerResult;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<HandlerResult> {
+        match catch(|| self.0.as_mut().poll(cx)) {
+            Ok(poll) => poll,
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+fn catch<R, F: FnOnce() -> R>(f: F) -> Result<R, HandlerError> {
+    install_hook();
+    let was_catching = CATCHING.with(|cell| cell.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.with(|cell| cell.set(was_catching));
+
+    result.map_err(|payload| {
+        let message = panic_message(payload.as_ref());
+        let backtrace = BACKTRACE
+            .with(|cell| cell.borrow_mut().take())
+            .unwrap_or_default();
+        HandlerError::new(&format!("handler panicked: {message}"))
+            .with_details(&format!("{message}\n\n{backtrace}"))
+    })
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic payload"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn catches_panic_of_handler() {
+        let Err(error) = catch_panic(|| panic!("boom")) else {
+            panic!("expected a handler error");
+        };
+
+        assert_eq!(error.message, "handler panicked: boom");
+        assert!(error.details.unwrap().starts_with("boom\n\n"));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn catches_panic_of_async_handler() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let handler = || -> HandlerFuture {
+            Box::pin(async {
+                tokio::task::yield_now().await;
+                panic!("boom {}", 42)
+            })
+        };
+
+        let Err(error) = runtime.block_on(catch_panic_async(handler)) else {
+            panic!("expected a handler error");
+        };
+
+        assert_eq!(error.message, "handler panicked: boom 42");
+    }
+}
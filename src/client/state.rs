@@ -0,0 +1,43 @@
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::{FromTask, HandlerError, TaskContext};
+
+/// Application state shared with all handlers, one value per type.
+#[derive(Default, Clone)]
+pub(crate) struct StateMap(HashMap<TypeId, Arc<dyn Any + Send + Sync>>);
+
+impl StateMap {
+    pub(crate) fn insert<S: Send + Sync + 'static>(&mut self, state: S) {
+        self.0.insert(TypeId::of::<S>(), Arc::new(state));
+    }
+
+    pub(crate) fn get<S: Send + Sync + 'static>(&self) -> Option<Arc<S>> {
+        self.0
+            .get(&TypeId::of::<S>())
+            .and_then(|state| Arc::clone(state).downcast().ok())
+    }
+}
+
+/// State added with `Client::with_state`, extraction fails if there is no state of type `S`.
+pub struct State<S>(pub Arc<S>);
+
+impl<S> Deref for State<S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.0
+    }
+}
+
+impl<S: Send + Sync + 'static> FromTask for State<S> {
+    type Output<'a> = State<S>;
+
+    fn from_task<'a>(context: &'a TaskContext<'_>) -> Result<Self::Output<'a>, HandlerError> {
+        context.state().map(State).ok_or_else(|| {
+            HandlerError::new(&format!("no state of type {} added", type_name::<S>()))
+        })
+    }
+}
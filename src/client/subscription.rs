@@ -1,25 +1,48 @@
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
-use crate::{Config, Error, Result, TaskHandler, Topic};
+#[cfg(feature = "blocking")]
+use crate::client::extract::HandlerService;
+use crate::{Config, Error, ProcessVariables, Result, Topic};
+#[cfg(feature = "blocking")]
+use crate::{Handler, TaskHandler};
 
-pub struct SubscriptionManager {
+/// Client side settings of a subscription, which are not sent to the engine.
+#[derive(Default, Clone)]
+pub struct SubscriptionSettings {
+    /// Extend the lock of fetched tasks periodically while they wait for or run in a handler.
+    pub auto_extend_lock: bool,
+    /// Maximum number of tasks of the topic handled at the same time.
+    pub max_concurrency: Option<usize>,
+}
+
+/// Subscriptions of a client with their handlers of type `H`.
+pub struct SubscriptionManager<H: ?Sized> {
     subscriptions: HashMap<String, Arc<RwLock<Topic>>>,
-    handlers: HashMap<String, TaskHandler>,
+    handlers: HashMap<String, Arc<H>>,
+    settings: HashMap<String, SubscriptionSettings>,
+}
+
+impl<H: ?Sized> Default for SubscriptionManager<H> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl SubscriptionManager {
+impl<H: ?Sized> SubscriptionManager<H> {
     pub fn new() -> Self {
         Self {
             subscriptions: HashMap::new(),
             handlers: HashMap::new(),
+            settings: HashMap::new(),
         }
     }
 
     pub fn add_subscription(
         &mut self,
         topic: &Arc<RwLock<Topic>>,
-        handler: TaskHandler,
+        handler: Arc<H>,
+        settings: SubscriptionSettings,
     ) -> Result<()> {
         // FIXME cleanup
         let topic2 = Arc::clone(topic);
@@ -32,6 +55,7 @@ impl SubscriptionManager {
         } else {
             self.subscriptions.insert(key.to_owned(), topic2);
             self.handlers.insert(key.to_owned(), handler);
+            self.settings.insert(key.to_owned(), settings);
             Ok(())
         }
     }
@@ -43,26 +67,64 @@ impl SubscriptionManager {
             .collect()
     }
 
-    pub fn get_handler(&self, topic_name: &str) -> Option<&TaskHandler> {
-        self.handlers.get(topic_name)
+    pub fn get_handler(&self, topic_name: &str) -> Option<Arc<H>> {
+        self.handlers.get(topic_name).cloned()
+    }
+
+    pub fn get_settings(&self, topic_name: &str) -> Option<&SubscriptionSettings> {
+        self.settings.get(topic_name)
+    }
+
+    /// Subscriptions of topics below their `max_concurrency`, given the number of queued or
+    /// running tasks per topic. Also returns how many tasks can be fetched for them, which
+    /// is unlimited if one of them has no `max_concurrency`.
+    pub fn subscriptions_below_limit(
+        &self,
+        active_tasks: &HashMap<String, usize>,
+    ) -> (Vec<Topic>, Option<usize>) {
+        let mut topics = Vec::new();
+        let mut room = Some(0);
+        for (topic_name, topic) in &self.subscriptions {
+            let limit = self
+                .settings
+                .get(topic_name)
+                .and_then(|settings| settings.max_concurrency);
+            let active = active_tasks.get(topic_name).copied().unwrap_or(0);
+            match limit {
+                Some(limit) if active >= limit => continue,
+                Some(limit) => room = room.map(|room| room + limit - active),
+                None => room = None,
+            }
+            topics.push(topic.read().unwrap().clone());
+        }
+        (topics, room)
     }
 }
 
-pub struct SubscriptionBuilder {
+pub struct SubscriptionBuilder<H: ?Sized> {
     topic: Arc<RwLock<Topic>>,
-    handler: Option<TaskHandler>,
-    topic_subscription_manager: Arc<RwLock<SubscriptionManager>>,
+    handler: Option<Arc<H>>,
+    settings: SubscriptionSettings,
+    topic_subscription_manager: Arc<RwLock<SubscriptionManager<H>>>,
 }
 
-impl SubscriptionBuilder {
+#[cfg(feature = "blocking")]
+impl SubscriptionBuilder<dyn TaskHandler> {
+    pub fn handler<Args: 'static, T: Handler<Args>>(&mut self, handler: T) -> &mut Self {
+        self.set_handler(Arc::new(HandlerService::new(handler)))
+    }
+}
+
+impl<H: ?Sized> SubscriptionBuilder<H> {
     pub fn new(
         config: &Arc<Config>,
-        topic_subscription_manager: &Arc<RwLock<SubscriptionManager>>,
+        topic_subscription_manager: &Arc<RwLock<SubscriptionManager<H>>>,
         topic_name: &str,
     ) -> Self {
         let instance = Self {
             topic: Arc::new(RwLock::new(Default::default())),
             handler: None,
+            settings: Default::default(),
             topic_subscription_manager: Arc::clone(topic_subscription_manager),
         };
 
@@ -74,7 +136,7 @@ impl SubscriptionBuilder {
         instance
     }
 
-    pub fn handler(&mut self, handler: TaskHandler) -> &mut Self {
+    pub(crate) fn set_handler(&mut self, handler: Arc<H>) -> &mut Self {
         self.handler = Some(handler);
         self
     }
@@ -84,11 +146,32 @@ impl SubscriptionBuilder {
         self
     }
 
+    /// Keeps the lock of fetched tasks alive until their handler returns, by extending it
+    /// every half `lock_duration`. Without it, locks are only extended while the tasks wait
+    /// for their handler to start.
+    pub fn auto_extend_lock(&mut self, auto_extend_lock: bool) -> &mut Self {
+        self.settings.auto_extend_lock = auto_extend_lock;
+        self
+    }
+
+    /// Limits the number of tasks of this topic handled at the same time, to leave room for
+    /// other topics in the worker pool (`Config::with_concurrency`). No more tasks of the
+    /// topic are fetched while it is at its limit.
+    pub fn max_concurrency(&mut self, max_concurrency: usize) -> &mut Self {
+        self.settings.max_concurrency = Some(max_concurrency.max(1));
+        self
+    }
+
     pub fn variables(&mut self, variables: Option<Vec<String>>) -> &mut Self {
         self.topic.write().unwrap().variables = variables;
         self
     }
 
+    /// Fetches exactly the variables read by `T::from_variables`.
+    pub fn variables_from<T: ProcessVariables>(&mut self) -> &mut Self {
+        self.variables(Some(T::variable_names()))
+    }
+
     pub fn local_variables(&mut self, local_variables: Option<bool>) -> &mut Self {
         self.topic.write().unwrap().local_variables = local_variables;
         self
@@ -168,8 +251,63 @@ impl SubscriptionBuilder {
             ))
         } else {
             let mut tsm = self.topic_subscription_manager.write().unwrap();
-            tsm.add_subscription(&self.topic, self.handler.take().unwrap())?;
+            tsm.add_subscription(
+                &self.topic,
+                self.handler.take().unwrap(),
+                self.settings.clone(),
+            )?;
             Ok(())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscribe(
+        subscription_manager: &mut SubscriptionManager<()>,
+        topic_name: &str,
+        max_concurrency: Option<usize>,
+    ) {
+        let topic = Arc::new(RwLock::new(Topic::new(topic_name)));
+        let settings = SubscriptionSettings {
+            max_concurrency,
+            ..Default::default()
+        };
+        subscription_manager
+            .add_subscription(&topic, Arc::new(()), settings)
+            .unwrap();
+    }
+
+    fn topic_names(topics: &[Topic]) -> Vec<&str> {
+        let mut topic_names: Vec<_> = topics.iter().map(|t| t.topic_name.as_str()).collect();
+        topic_names.sort();
+        topic_names
+    }
+
+    #[test]
+    fn leaves_out_topics_at_their_limit() {
+        let mut subscription_manager = SubscriptionManager::new();
+        subscribe(&mut subscription_manager, "full", Some(2));
+        subscribe(&mut subscription_manager, "limited", Some(3));
+        let active_tasks = HashMap::from([("full".to_string(), 2), ("limited".to_string(), 1)]);
+
+        let (topics, room) = subscription_manager.subscriptions_below_limit(&active_tasks);
+
+        assert_eq!(topic_names(&topics), ["limited"]);
+        assert_eq!(room, Some(2));
+    }
+
+    #[test]
+    fn fetches_unlimited_with_topic_without_limit() {
+        let mut subscription_manager = SubscriptionManager::new();
+        subscribe(&mut subscription_manager, "limited", Some(3));
+        subscribe(&mut subscription_manager, "unlimited", None);
+
+        let (topics, room) = subscription_manager.subscriptions_below_limit(&HashMap::new());
+
+        assert_eq!(topic_names(&topics), ["limited", "unlimited"]);
+        assert_eq!(room, None);
+    }
+}
@@ -1,14 +1,30 @@
 use std::fmt::{Debug, Display, Formatter};
 
+use crate::{ErrorResponse, VariableType};
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug)]
 pub enum Error {
     BackendRequestError(String),
+    /// The engine could not be reached or the response could not be read.
+    RequestError(reqwest::Error),
+    /// The engine answered with an error status.
+    EngineError(EngineError),
     TopicSubscriptionError(String),
     ClientConfigurationError(reqwest::Error),
     ClientError(String),
-    VariableParseError,
+    /// Variables could not be mapped to or from a Rust value. `name` is the variable which
+    /// failed, if the error is specific to one variable.
+    VariableParseError {
+        name: Option<String>,
+        reason: String,
+    },
+    /// A variable has another type than requested, or an unknown type if `expected` is none.
+    VariableTypeError {
+        expected: Option<VariableType>,
+        actual: String,
+    },
 }
 
 impl Display for Error {
@@ -18,4 +34,97 @@ impl Display for Error {
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::RequestError(e) | Error::ClientConfigurationError(e) => Some(e),
+            Error::EngineError(e) => Some(&e.source),
+            _ => None,
+        }
+    }
+}
+
+/// Error response of the engine, e.g. when a task does not exist anymore or is locked by
+/// another worker.
+#[derive(Debug)]
+pub struct EngineError {
+    /// HTTP status code
+    pub status: u16,
+    /// Type of the exception thrown by the engine, e.g. `RestException`
+    pub kind: Option<String>,
+    pub message: String,
+    /// Error code of the engine, if configured
+    pub code: Option<i32>,
+    source: reqwest::Error,
+}
+
+impl EngineError {
+    pub(crate) fn from_response(status: u16, body: &str, source: reqwest::Error) -> Self {
+        let response: ErrorResponse = serde_json::from_str(body).unwrap_or_default();
+        Self {
+            status,
+            kind: response.kind,
+            message: response.message.unwrap_or_else(|| body.to_string()),
+            code: response.code,
+            source,
+        }
+    }
+
+    /// The task does not exist anymore, e.g. because it was completed or cancelled.
+    pub fn is_not_found(&self) -> bool {
+        self.status == 404
+    }
+
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.status)
+    }
+
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source() -> reqwest::Error {
+        reqwest::Client::new()
+            .get("not a url")
+            .build()
+            .expect_err("invalid url")
+    }
+
+    #[test]
+    fn parses_engine_error_body() {
+        let body = r#"{"type":"RestException","message":"External task is locked","code":22222}"#;
+
+        let e = EngineError::from_response(400, body, source());
+
+        assert_eq!(e.kind.as_deref(), Some("RestException"));
+        assert_eq!(e.message, "External task is locked");
+        assert_eq!(e.code, Some(22222));
+    }
+
+    #[test]
+    fn keeps_body_which_is_not_json() {
+        let e = EngineError::from_response(502, "<html>Bad Gateway</html>", source());
+
+        assert_eq!(e.kind, None);
+        assert_eq!(e.message, "<html>Bad Gateway</html>");
+        assert_eq!(e.code, None);
+    }
+
+    #[test]
+    fn classifies_status() {
+        let not_found = EngineError::from_response(404, "", source());
+        assert!(not_found.is_not_found());
+        assert!(not_found.is_client_error());
+        assert!(!not_found.is_server_error());
+
+        let server_error = EngineError::from_response(500, "", source());
+        assert!(!server_error.is_not_found());
+        assert!(!server_error.is_client_error());
+        assert!(server_error.is_server_error());
+    }
+}
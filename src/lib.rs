@@ -1,9 +1,125 @@
 extern crate core;
 
+#[cfg(not(any(feature = "blocking", feature = "async")))]
+compile_error!("at least one of the features `blocking` and `async` must be enabled");
+
+#[cfg(feature = "async")]
+pub use crate::asynchronous::*;
 pub use crate::client::*;
 pub use crate::error::*;
 pub use crate::service::*;
+#[cfg(feature = "macros")]
+pub use camunda_external_task_client_macros::*;
 
+#[cfg(feature = "async")]
+mod asynchronous;
 mod client;
 mod error;
 mod service;
+
+/// Helpers of the generated code of the macros, not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    use std::collections::HashMap;
+
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_json::Value;
+
+    #[cfg(all(feature = "macros", feature = "blocking"))]
+    pub use inventory;
+
+    use crate::{Error, Result, Variable, VariableType};
+
+    /// Handler registered by `#[external_task]`, subscribed by `Client::register_all`.
+    #[cfg(all(feature = "macros", feature = "blocking"))]
+    pub struct ExternalTask {
+        pub topic: &'static str,
+        pub lock_duration: Option<u32>,
+        pub variables: Option<&'static [&'static str]>,
+        pub handler: fn() -> std::sync::Arc<dyn crate::TaskHandler>,
+    }
+
+    #[cfg(all(feature = "macros", feature = "blocking"))]
+    inventory::collect!(ExternalTask);
+
+    /// Registers an `#[external_task]` handler, which needs the blocking client.
+    #[cfg(all(feature = "macros", feature = "blocking"))]
+    #[macro_export]
+    #[doc(hidden)]
+    macro_rules! __submit_external_task {
+        ($topic:expr, $lock_duration:expr, $variables:expr, $handler:path) => {
+            $crate::__private::inventory::submit! {
+                $crate::__private::ExternalTask {
+                    topic: $topic,
+                    lock_duration: $lock_duration,
+                    variables: $variables,
+                    handler: || -> ::std::sync::Arc<dyn $crate::TaskHandler> {
+                        $crate::__private::task_handler($handler)
+                    },
+                }
+            }
+        };
+    }
+
+    #[cfg(all(feature = "macros", not(feature = "blocking")))]
+    #[macro_export]
+    #[doc(hidden)]
+    macro_rules! __submit_external_task {
+        ($($args:tt)*) => {
+            ::core::compile_error!(
+                "`#[external_task]` handlers run on the blocking `Client`, enable the `blocking` feature"
+            );
+        };
+    }
+
+    #[cfg(feature = "macros")]
+    pub use __submit_external_task as submit_external_task;
+
+    #[cfg(all(feature = "macros", feature = "blocking"))]
+    pub fn task_handler<Args: 'static, T: crate::Handler<Args>>(
+        handler: T,
+    ) -> std::sync::Arc<dyn crate::TaskHandler> {
+        std::sync::Arc::new(crate::client::extract::HandlerService::new(handler))
+    }
+
+    pub fn read_variable<T: DeserializeOwned>(
+        variables: &HashMap<String, Variable>,
+        name: &str,
+    ) -> Result<T> {
+        let result = match variables.get(name) {
+            Some(variable) => variable.deserialize_as(),
+            // only optional fields can be read from a missing variable
+            None => serde_json::from_value(Value::Null).map_err(|_| Error::VariableParseError {
+                name: None,
+                reason: "variable is missing".to_string(),
+            }),
+        };
+        result.map_err(|e| with_name(e, name))
+    }
+
+    pub fn write_variable<T: Serialize + ?Sized>(
+        variables: &mut HashMap<String, Variable>,
+        name: &str,
+        value: &T,
+        variable_type: Option<VariableType>,
+    ) -> Result<()> {
+        let variable = match variable_type {
+            Some(variable_type) => Variable::from_serializable_as(value, variable_type),
+            None => Variable::from_serializable(value),
+        }
+        .map_err(|e| with_name(e, name))?;
+        variables.insert(name.to_string(), variable);
+        Ok(())
+    }
+
+    fn with_name(e: Error, name: &str) -> Error {
+        match e {
+            Error::VariableParseError { name: None, reason } => Error::VariableParseError {
+                name: Some(name.to_string()),
+                reason,
+            },
+            e => e,
+        }
+    }
+}
@@ -2,11 +2,11 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use log::{error, trace};
-use reqwest::blocking::{Client, ClientBuilder, Response};
-use reqwest::Result as ReqwestResult;
-use serde::Serialize;
+use reqwest::blocking::{Client, ClientBuilder};
 
-use crate::{CompleteRequest, Config, Error, FetchAndLockRequest, Result, Task, Variable};
+use crate::client::outcome::outcome_request;
+use crate::service::request::{self, EngineRequest};
+use crate::{Config, Error, FetchAndLockRequest, HandlerResult, Result, Task, Variable};
 
 pub struct EngineService {
     config: Arc<Config>,
@@ -17,18 +17,31 @@ impl EngineService {
     pub fn new(config: Arc<Config>) -> Result<Self> {
         // TODO offer attributes to configure client or to pass it via parameter
         let client = ClientBuilder::new()
+            .timeout(request::timeout(&config))
             .build()
-            .map_err(|e| Error::ClientConfigurationError(e))?;
+            .map_err(Error::ClientConfigurationError)?;
         Ok(Self { config, client })
     }
 
-    fn post<T: Serialize + ?Sized>(&self, path: &str, body: &T) -> ReqwestResult<Response> {
-        let mut url = self.config.base_url().to_owned();
-        url.push_str(path);
-        let request = self.client.post(url).json(body).build().unwrap();
+    fn send(&self, request: EngineRequest) -> Result<String> {
+        let mut request_builder = self.client.post(request.url(&self.config));
+        if let Some(body) = &request.body {
+            request_builder = request_builder.json(body);
+        }
+        let request = request_builder.build().map_err(Error::RequestError)?;
         trace!("{request:?}");
         trace!("{:?}", request.body());
-        self.client.execute(request)
+        let response = self.client.execute(request).map_err(|e| {
+            error!("{e:?}");
+            Error::RequestError(e)
+        })?;
+        trace!("{response:?}");
+
+        let status = response.status();
+        let status_error = response.error_for_status_ref().err();
+        let response_text = response.text().map_err(Error::RequestError)?;
+        trace!("{response_text:?}");
+        request::response_text(status, status_error, response_text)
     }
 
     pub fn complete(
@@ -37,51 +50,73 @@ impl EngineService {
         variables: Option<HashMap<String, Variable>>,
         local_variables: Option<HashMap<String, Variable>>,
     ) -> Result<()> {
-        let path = format!("/external-task/{}/complete", task.id);
-        let mut request = CompleteRequest {
-            worker_id: self.config.worker_id().into(),
-            variables: Default::default(),
-            local_variables: Default::default(),
-        };
-        if let Some(variables) = variables {
-            request.variables = variables;
-        }
-        if let Some(local_variables) = local_variables {
-            request.local_variables = local_variables;
-        }
-        let res = self.post(path.as_ref(), &request);
+        let request = EngineRequest::complete(&self.config, task, variables, local_variables)?;
+        self.send(request).map(|_| ())
+    }
 
-        match res {
-            Ok(response) => {
-                trace!("{response:?}");
-                let response_text = response.text().unwrap();
-                trace!("{response_text:?}");
-                Ok(())
-            }
-            Err(e) => {
-                error!("{e:?}");
-                Err(Error::BackendRequestError("request failed".to_string()))
-            }
-        }
+    /// Reports a business error which can be caught by a BPMN error boundary event.
+    pub fn handle_bpmn_error(
+        &self,
+        task: &Task,
+        error_code: &str,
+        error_message: Option<&str>,
+        variables: Option<HashMap<String, Variable>>,
+    ) -> Result<()> {
+        let request = EngineRequest::handle_bpmn_error(
+            &self.config,
+            task,
+            error_code,
+            error_message,
+            variables,
+        )?;
+        self.send(request).map(|_| ())
     }
 
-    pub fn fetch_and_lock(&self, request: FetchAndLockRequest) -> Result<Vec<Task>> {
-        let res = self.post("/external-task/fetchAndLock", &request);
+    /// Reports a failure to execute the task. If `retries` is 0 an incident is created,
+    /// otherwise the task can be fetched again after `retry_timeout` milliseconds.
+    #[allow(clippy::too_many_arguments)]
+    pub fn handle_failure(
+        &self,
+        task: &Task,
+        error_message: &str,
+        error_details: Option<&str>,
+        retries: u32,
+        retry_timeout: u64,
+        variables: Option<HashMap<String, Variable>>,
+        local_variables: Option<HashMap<String, Variable>>,
+    ) -> Result<()> {
+        let request = EngineRequest::handle_failure(
+            &self.config,
+            task,
+            error_message,
+            error_details,
+            retries,
+            retry_timeout,
+            variables,
+            local_variables,
+        )?;
+        self.send(request).map(|_| ())
+    }
 
-        match res {
-            Ok(response) => {
-                trace!("{response:?}");
-                let response_text = response.text().unwrap();
-                trace!("{response_text:?}");
-                serde_json::from_str(response_text.as_ref()).map_err(|e| {
-                    error!("{e:?}");
-                    Error::BackendRequestError("cannot deserialize json".to_string())
-                })
-            }
-            Err(e) => {
-                error!("{e:?}");
-                Err(Error::BackendRequestError("request failed".to_string()))
-            }
-        }
+    /// Extends the lock of the task by `new_duration` milliseconds, counted from now.
+    pub fn extend_lock(&self, task: &Task, new_duration: u64) -> Result<()> {
+        let request = EngineRequest::extend_lock(&self.config, task, new_duration)?;
+        self.send(request).map(|_| ())
+    }
+
+    /// Releases the lock of the task, so that it can be fetched again immediately.
+    pub fn unlock(&self, task: &Task) -> Result<()> {
+        self.send(EngineRequest::unlock(task)).map(|_| ())
+    }
+
+    /// Makes the engine call matching the result of a handler.
+    pub(crate) fn report_outcome(&self, task: &Task, result: HandlerResult) -> Result<()> {
+        self.send(outcome_request(&self.config, task, result)?)
+            .map(|_| ())
+    }
+
+    pub fn fetch_and_lock(&self, request: FetchAndLockRequest) -> Result<Vec<Task>> {
+        let response_text = self.send(EngineRequest::fetch_and_lock(&request)?)?;
+        request::parse_tasks(&response_text)
     }
 }
@@ -1,5 +1,14 @@
+#[cfg(feature = "blocking")]
 pub use engine_service::*;
 pub use model::*;
+pub use process_variables::*;
+pub use variable::*;
+pub use variable_mapping::*;
 
+#[cfg(feature = "blocking")]
 mod engine_service;
 mod model;
+mod process_variables;
+pub(crate) mod request;
+mod variable;
+mod variable_mapping;
@@ -4,6 +4,17 @@ use std::fmt::{Display, Formatter};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+/// Body of an error response of the engine.
+#[derive(Deserialize, Default, Clone)]
+pub struct ErrorResponse {
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    #[serde(rename = "message")]
+    pub message: Option<String>,
+    #[serde(rename = "code")]
+    pub code: Option<i32>,
+}
+
 #[derive(Serialize, Default, Clone)]
 pub struct CompleteRequest {
     #[serde(rename = "workerId")]
@@ -14,6 +25,47 @@ pub struct CompleteRequest {
     pub local_variables: HashMap<String, Variable>,
 }
 
+#[derive(Serialize, Default, Clone)]
+pub struct BpmnErrorRequest {
+    #[serde(rename = "workerId")]
+    pub worker_id: String,
+    #[serde(rename = "errorCode")]
+    pub error_code: String,
+    #[serde(rename = "errorMessage")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_message: Option<String>,
+    #[serde(rename = "variables")]
+    pub variables: HashMap<String, Variable>,
+}
+
+#[derive(Serialize, Default, Clone)]
+pub struct HandleFailureRequest {
+    #[serde(rename = "workerId")]
+    pub worker_id: String,
+    #[serde(rename = "errorMessage")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_message: Option<String>,
+    #[serde(rename = "errorDetails")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_details: Option<String>,
+    #[serde(rename = "retries")]
+    pub retries: u32,
+    #[serde(rename = "retryTimeout")]
+    pub retry_timeout: u64,
+    #[serde(rename = "variables")]
+    pub variables: HashMap<String, Variable>,
+    #[serde(rename = "localVariables")]
+    pub local_variables: HashMap<String, Variable>,
+}
+
+#[derive(Serialize, Default, Clone)]
+pub struct ExtendLockRequest {
+    #[serde(rename = "workerId")]
+    pub worker_id: String,
+    #[serde(rename = "newDuration")]
+    pub new_duration: u64,
+}
+
 #[derive(Serialize, Default, Clone)]
 pub struct FetchAndLockRequest {
     #[serde(rename = "workerId")]
@@ -31,9 +83,10 @@ pub struct FetchAndLockRequest {
 
 impl FetchAndLockRequest {
     pub fn new(worker_id: &str) -> Self {
-        let mut instance = Self::default();
-        instance.worker_id = worker_id.to_string();
-        instance
+        Self {
+            worker_id: worker_id.to_string(),
+            ..Default::default()
+        }
     }
 
     pub fn topics(&mut self) -> &mut Vec<Topic> {
@@ -66,7 +119,7 @@ pub struct Task {
     #[serde(rename = "processInstanceId")]
     pub process_instance_id: String,
     #[serde(rename = "retries")]
-    pub retries: Option<String>,
+    pub retries: Option<u32>,
     #[serde(rename = "suspended")]
     pub suspended: bool,
     #[serde(rename = "workerId")]
@@ -77,10 +130,14 @@ pub struct Task {
     pub tenant_id: Option<String>,
     #[serde(rename = "variables")]
     pub variables: HashMap<String, Variable>,
+    /// Priority of the task, which can be negative.
     #[serde(rename = "priority")]
-    pub priority: u32,
+    pub priority: i64,
     #[serde(rename = "businessKey")]
     pub business_key: Option<String>,
+    /// Creation time, only sent by engines since 7.21.
+    #[serde(rename = "createTime")]
+    pub create_time: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Default, Clone)]
@@ -117,6 +174,15 @@ pub struct ValueInfo {
     pub object_type_name: Option<String>,
     #[serde(rename = "serializationDataFormat")]
     pub serialization_data_format: Option<String>,
+    #[serde(rename = "filename")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    #[serde(rename = "mimetype")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(rename = "encoding")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
 }
 
 #[derive(Serialize, Default, Clone)]
@@ -168,9 +234,10 @@ pub struct Topic {
 
 impl Topic {
     pub fn new(topic_name: &str) -> Self {
-        let mut instance = Self::default();
-        instance.topic_name = topic_name.to_string();
-        instance.lock_duration = 50000;
-        instance
+        Self {
+            topic_name: topic_name.to_string(),
+            lock_duration: 50000,
+            ..Default::default()
+        }
     }
 }
@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+
+use crate::{Result, Variable};
+
+/// Conversion of a struct to and from process variables, usually derived with
+/// `#[derive(ProcessVariables)]` of the `macros` feature.
+///
+/// ```
+/// # #[cfg(feature = "macros")]
+/// # fn main() -> camunda_external_task_client_rust::Result<()> {
+/// use camunda_external_task_client_rust::*;
+///
+/// #[derive(ProcessVariables)]
+/// struct LoanRequest {
+///     amount: i64,
+///     #[variable(name = "creditScores", type = "json")]
+///     credit_scores: Vec<u8>,
+///     #[variable(local)]
+///     approved: Option<bool>,
+/// }
+///
+/// assert_eq!(LoanRequest::variable_names(), vec!["amount", "creditScores", "approved"]);
+///
+/// let request = LoanRequest { amount: 1000, credit_scores: vec![9, 1], approved: None };
+/// let variables = request.to_variables()?;
+/// assert_eq!(variables["creditScores"].kind, "Json");
+/// assert!(request.to_local_variables()?.contains_key("approved"));
+///
+/// let request = LoanRequest::from_variables(&variables)?;
+/// assert_eq!(request.approved, None);
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "macros"))]
+/// # fn main() {}
+/// ```
+pub trait ProcessVariables: Sized {
+    /// Names of all variables of the struct, to fetch only those.
+    fn variable_names() -> Vec<String>;
+
+    /// Reads the struct from variables. Missing variables are only accepted for `Option`
+    /// fields.
+    fn from_variables(variables: &HashMap<String, Variable>) -> Result<Self>;
+
+    /// Variables of all fields which are not marked as `local`.
+    fn to_variables(&self) -> Result<HashMap<String, Variable>>;
+
+    /// Variables of the fields marked as `local`.
+    fn to_local_variables(&self) -> Result<HashMap<String, Variable>>;
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use log::error;
+use reqwest::StatusCode;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    BpmnErrorRequest, CompleteRequest, Config, EngineError, Error, ExtendLockRequest,
+    FetchAndLockRequest, HandleFailureRequest, Result, Task, Variable,
+};
+
+/// Timeout of requests, extended by the async response timeout for long polling.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Request to the engine, built the same way by the blocking and the async engine service,
+/// which only differ in sending it.
+pub(crate) struct EngineRequest {
+    pub path: String,
+    pub body: Option<Value>,
+}
+
+impl EngineRequest {
+    pub fn url(&self, config: &Config) -> String {
+        format!("{}{}", config.base_url(), self.path)
+    }
+
+    pub fn complete(
+        config: &Config,
+        task: &Task,
+        variables: Option<HashMap<String, Variable>>,
+        local_variables: Option<HashMap<String, Variable>>,
+    ) -> Result<Self> {
+        let request = CompleteRequest {
+            worker_id: config.worker_id().into(),
+            variables: variables.unwrap_or_default(),
+            local_variables: local_variables.unwrap_or_default(),
+        };
+        Self::new(format!("/external-task/{}/complete", task.id), &request)
+    }
+
+    pub fn handle_bpmn_error(
+        config: &Config,
+        task: &Task,
+        error_code: &str,
+        error_message: Option<&str>,
+        variables: Option<HashMap<String, Variable>>,
+    ) -> Result<Self> {
+        let request = BpmnErrorRequest {
+            worker_id: config.worker_id().into(),
+            error_code: error_code.to_string(),
+            error_message: error_message.map(|m| m.to_string()),
+            variables: variables.unwrap_or_default(),
+        };
+        Self::new(format!("/external-task/{}/bpmnError", task.id), &request)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn handle_failure(
+        config: &Config,
+        task: &Task,
+        error_message: &str,
+        error_details: Option<&str>,
+        retries: u32,
+        retry_timeout: u64,
+        variables: Option<HashMap<String, Variable>>,
+        local_variables: Option<HashMap<String, Variable>>,
+    ) -> Result<Self> {
+        let request = HandleFailureRequest {
+            worker_id: config.worker_id().into(),
+            error_message: Some(error_message.to_string()),
+            error_details: error_details.map(|d| d.to_string()),
+            retries,
+            retry_timeout,
+            variables: variables.unwrap_or_default(),
+            local_variables: local_variables.unwrap_or_default(),
+        };
+        Self::new(format!("/external-task/{}/failure", task.id), &request)
+    }
+
+    pub fn extend_lock(config: &Config, task: &Task, new_duration: u64) -> Result<Self> {
+        let request = ExtendLockRequest {
+            worker_id: config.worker_id().into(),
+            new_duration,
+        };
+        Self::new(format!("/external-task/{}/extendLock", task.id), &request)
+    }
+
+    pub fn unlock(task: &Task) -> Self {
+        Self {
+            path: format!("/external-task/{}/unlock", task.id),
+            body: None,
+        }
+    }
+
+    pub fn fetch_and_lock(request: &FetchAndLockRequest) -> Result<Self> {
+        Self::new("/external-task/fetchAndLock".into(), request)
+    }
+
+    fn new<T: Serialize>(path: String, body: &T) -> Result<Self> {
+        let body = serde_json::to_value(body).map_err(|e| {
+            error!("{e:?}");
+            Error::BackendRequestError("cannot serialize json".to_string())
+        })?;
+        Ok(Self {
+            path,
+            body: Some(body),
+        })
+    }
+}
+
+/// Timeout of the http client, long enough for long polling.
+pub(crate) fn timeout(config: &Config) -> Duration {
+    let async_response_timeout = config.async_response_timeout().unwrap_or_default();
+    REQUEST_TIMEOUT + Duration::from_millis(async_response_timeout.into())
+}
+
+/// Maps the response to its text, or to an `EngineError` if the status is an error.
+pub(crate) fn response_text(
+    status: StatusCode,
+    status_error: Option<reqwest::Error>,
+    response_text: String,
+) -> Result<String> {
+    match status_error {
+        Some(source) => {
+            let e = EngineError::from_response(status.as_u16(), &response_text, source);
+            error!("{e:?}");
+            Err(Error::EngineError(e))
+        }
+        None => Ok(response_text),
+    }
+}
+
+pub(crate) fn parse_tasks(response_text: &str) -> Result<Vec<Task>> {
+    serde_json::from_str(response_text).map_err(|e| {
+        error!("{e:?}");
+        Error::BackendRequestError("cannot deserialize json".to_string())
+    })
+}
@@ -0,0 +1,561 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::{Error, Result, ValueInfo, Variable};
+
+/// Value types of Camunda variables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariableType {
+    String,
+    Boolean,
+    Short,
+    Integer,
+    Long,
+    Double,
+    Date,
+    Json,
+    Xml,
+    Object,
+    File,
+    Bytes,
+    Null,
+}
+
+impl VariableType {
+    /// Type name used by the REST API.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VariableType::String => "String",
+            VariableType::Boolean => "Boolean",
+            VariableType::Short => "Short",
+            VariableType::Integer => "Integer",
+            VariableType::Long => "Long",
+            VariableType::Double => "Double",
+            VariableType::Date => "Date",
+            VariableType::Json => "Json",
+            VariableType::Xml => "Xml",
+            VariableType::Object => "Object",
+            VariableType::File => "File",
+            VariableType::Bytes => "Bytes",
+            VariableType::Null => "Null",
+        }
+    }
+}
+
+impl FromStr for VariableType {
+    type Err = Error;
+
+    /// Parses a type name case-insensitively, like the engine does.
+    fn from_str(s: &str) -> Result<Self> {
+        [
+            VariableType::String,
+            VariableType::Boolean,
+            VariableType::Short,
+            VariableType::Integer,
+            VariableType::Long,
+            VariableType::Double,
+            VariableType::Date,
+            VariableType::Json,
+            VariableType::Xml,
+            VariableType::Object,
+            VariableType::File,
+            VariableType::Bytes,
+            VariableType::Null,
+        ]
+        .into_iter()
+        .find(|variable_type| variable_type.as_str().eq_ignore_ascii_case(s))
+        .ok_or_else(|| Error::VariableTypeError {
+            expected: None,
+            actual: s.to_string(),
+        })
+    }
+}
+
+impl Display for VariableType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Typed value of a variable.
+///
+/// Dates are kept in the format of the engine (`yyyy-MM-dd'T'HH:mm:ss.SSSZ`), bytes and
+/// file contents are base64 encoded and JSON and XML values are serialized documents.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariableValue {
+    String(String),
+    Boolean(bool),
+    Short(i16),
+    Integer(i32),
+    Long(i64),
+    Double(f64),
+    Date(String),
+    Json(String),
+    Xml(String),
+    Object {
+        value: Value,
+        object_type_name: Option<String>,
+        serialization_data_format: Option<String>,
+    },
+    File {
+        value: Option<String>,
+        filename: Option<String>,
+        mime_type: Option<String>,
+        encoding: Option<String>,
+    },
+    Bytes(String),
+    Null,
+}
+
+impl VariableValue {
+    pub fn variable_type(&self) -> VariableType {
+        match self {
+            VariableValue::String(_) => VariableType::String,
+            VariableValue::Boolean(_) => VariableType::Boolean,
+            VariableValue::Short(_) => VariableType::Short,
+            VariableValue::Integer(_) => VariableType::Integer,
+            VariableValue::Long(_) => VariableType::Long,
+            VariableValue::Double(_) => VariableType::Double,
+            VariableValue::Date(_) => VariableType::Date,
+            VariableValue::Json(_) => VariableType::Json,
+            VariableValue::Xml(_) => VariableType::Xml,
+            VariableValue::Object { .. } => VariableType::Object,
+            VariableValue::File { .. } => VariableType::File,
+            VariableValue::Bytes(_) => VariableType::Bytes,
+            VariableValue::Null => VariableType::Null,
+        }
+    }
+}
+
+impl From<VariableValue> for Variable {
+    fn from(value: VariableValue) -> Self {
+        let kind = value.variable_type().as_str();
+        match value {
+            VariableValue::String(v)
+            | VariableValue::Date(v)
+            | VariableValue::Json(v)
+            | VariableValue::Xml(v)
+            | VariableValue::Bytes(v) => Variable::new(kind, Value::String(v)),
+            VariableValue::Boolean(v) => Variable::new(kind, Value::Bool(v)),
+            VariableValue::Short(v) => Variable::new(kind, v.into()),
+            VariableValue::Integer(v) => Variable::new(kind, v.into()),
+            VariableValue::Long(v) => Variable::new(kind, v.into()),
+            VariableValue::Double(v) => Variable::new(kind, v.into()),
+            VariableValue::Object {
+                value,
+                object_type_name,
+                serialization_data_format,
+            } => Variable {
+                kind: kind.to_string(),
+                value,
+                value_info: Some(ValueInfo {
+                    object_type_name,
+                    serialization_data_format,
+                    ..Default::default()
+                }),
+            },
+            VariableValue::File {
+                value,
+                filename,
+                mime_type,
+                encoding,
+            } => Variable {
+                kind: kind.to_string(),
+                value: value.map_or(Value::Null, Value::String),
+                value_info: Some(ValueInfo {
+                    filename,
+                    mime_type,
+                    encoding,
+                    ..Default::default()
+                }),
+            },
+            VariableValue::Null => Variable::new(kind, Value::Null),
+        }
+    }
+}
+
+impl TryFrom<&Variable> for VariableValue {
+    type Error = Error;
+
+    fn try_from(variable: &Variable) -> Result<Self> {
+        let variable_type = variable.variable_type()?;
+        let value_info = variable.value_info.clone().unwrap_or_default();
+        let value = match variable_type {
+            VariableType::String => VariableValue::String(variable.as_str()?.to_string()),
+            VariableType::Boolean => VariableValue::Boolean(variable.as_bool()?),
+            VariableType::Short => VariableValue::Short(variable.as_i16()?),
+            VariableType::Integer => VariableValue::Integer(variable.as_i32()?),
+            VariableType::Long => VariableValue::Long(variable.as_i64()?),
+            VariableType::Double => VariableValue::Double(variable.as_f64()?),
+            VariableType::Date => VariableValue::Date(variable.as_date()?.to_string()),
+            VariableType::Json => VariableValue::Json(variable.string_value(variable_type)?),
+            VariableType::Xml => VariableValue::Xml(variable.as_xml()?.to_string()),
+            VariableType::Object => VariableValue::Object {
+                value: variable.value.clone(),
+                object_type_name: value_info.object_type_name,
+                serialization_data_format: value_info.serialization_data_format,
+            },
+            VariableType::File => VariableValue::File {
+                value: variable.value.as_str().map(|v| v.to_string()),
+                filename: value_info.filename,
+                mime_type: value_info.mime_type,
+                encoding: value_info.encoding,
+            },
+            VariableType::Bytes => VariableValue::Bytes(variable.string_value(variable_type)?),
+            VariableType::Null => VariableValue::Null,
+        };
+        Ok(value)
+    }
+}
+
+impl Variable {
+    pub fn string(value: impl Into<String>) -> Self {
+        VariableValue::String(value.into()).into()
+    }
+    pub fn boolean(value: bool) -> Self {
+        VariableValue::Boolean(value).into()
+    }
+    pub fn short(value: i16) -> Self {
+        VariableValue::Short(value).into()
+    }
+    pub fn integer(value: i32) -> Self {
+        VariableValue::Integer(value).into()
+    }
+    pub fn long(value: i64) -> Self {
+        VariableValue::Long(value).into()
+    }
+    pub fn double(value: f64) -> Self {
+        VariableValue::Double(value).into()
+    }
+    /// Date in the format `yyyy-MM-dd'T'HH:mm:ss.SSSZ`, e.g. `2013-06-30T21:33:00.000+0200`.
+    pub fn date(value: impl Into<String>) -> Self {
+        VariableValue::Date(value.into()).into()
+    }
+    /// Serialized JSON document.
+    pub fn json(value: impl Into<String>) -> Self {
+        VariableValue::Json(value.into()).into()
+    }
+    /// Serialized XML document.
+    pub fn xml(value: impl Into<String>) -> Self {
+        VariableValue::Xml(value.into()).into()
+    }
+    /// Serialized Java object, e.g. with `serialization_data_format` `application/json`.
+    pub fn object(
+        value: impl Into<String>,
+        object_type_name: &str,
+        serialization_data_format: &str,
+    ) -> Self {
+        VariableValue::Object {
+            value: Value::String(value.into()),
+            object_type_name: Some(object_type_name.to_string()),
+            serialization_data_format: Some(serialization_data_format.to_string()),
+        }
+        .into()
+    }
+    /// Base64 encoded file content.
+    pub fn file(value: impl Into<String>, filename: &str, mime_type: Option<&str>) -> Self {
+        VariableValue::File {
+            value: Some(value.into()),
+            filename: Some(filename.to_string()),
+            mime_type: mime_type.map(|m| m.to_string()),
+            encoding: None,
+        }
+        .into()
+    }
+    /// Base64 encoded bytes.
+    pub fn bytes(value: impl Into<String>) -> Self {
+        VariableValue::Bytes(value.into()).into()
+    }
+    pub fn null() -> Self {
+        VariableValue::Null.into()
+    }
+
+    pub fn variable_type(&self) -> Result<VariableType> {
+        self.kind.parse()
+    }
+
+    /// Converts the variable into its typed value.
+    pub fn typed_value(&self) -> Result<VariableValue> {
+        self.try_into()
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.value.is_null()
+    }
+
+    pub fn as_str(&self) -> Result<&str> {
+        self.expect_type(&[VariableType::String])?;
+        self.value
+            .as_str()
+            .ok_or_else(|| self.type_error(VariableType::String))
+    }
+
+    pub fn as_bool(&self) -> Result<bool> {
+        self.expect_type(&[VariableType::Boolean])?;
+        self.value
+            .as_bool()
+            .ok_or_else(|| self.type_error(VariableType::Boolean))
+    }
+
+    pub fn as_i16(&self) -> Result<i16> {
+        self.expect_type(&[VariableType::Short])?;
+        self.integer_value(VariableType::Short)
+    }
+
+    /// Returns the value of a `Short` or `Integer` variable.
+    pub fn as_i32(&self) -> Result<i32> {
+        self.expect_type(&[VariableType::Short, VariableType::Integer])?;
+        self.integer_value(VariableType::Integer)
+    }
+
+    /// Returns the value of a `Short`, `Integer` or `Long` variable.
+    pub fn as_i64(&self) -> Result<i64> {
+        self.expect_type(&[
+            VariableType::Short,
+            VariableType::Integer,
+            VariableType::Long,
+        ])?;
+        self.integer_value(VariableType::Long)
+    }
+
+    /// Returns the value of a `Double` variable, or of an integer variable as double.
+    pub fn as_f64(&self) -> Result<f64> {
+        self.expect_type(&[
+            VariableType::Short,
+            VariableType::Integer,
+            VariableType::Long,
+            VariableType::Double,
+        ])?;
+        self.value
+            .as_f64()
+            .ok_or_else(|| self.type_error(VariableType::Double))
+    }
+
+    pub fn as_date(&self) -> Result<&str> {
+        self.expect_type(&[VariableType::Date])?;
+        self.value
+            .as_str()
+            .ok_or_else(|| self.type_error(VariableType::Date))
+    }
+
+    /// Parses the serialized document of a `Json` variable.
+    pub fn as_json(&self) -> Result<Value> {
+        self.expect_type(&[VariableType::Json])?;
+        match &self.value {
+            Value::String(json) => {
+                serde_json::from_str(json).map_err(|_| self.type_error(VariableType::Json))
+            }
+            value => Ok(value.clone()),
+        }
+    }
+
+    pub fn as_xml(&self) -> Result<&str> {
+        self.expect_type(&[VariableType::Xml])?;
+        self.value
+            .as_str()
+            .ok_or_else(|| self.type_error(VariableType::Xml))
+    }
+
+    fn expect_type(&self, expected: &[VariableType]) -> Result<()> {
+        let variable_type = self.variable_type()?;
+        if expected.contains(&variable_type) {
+            Ok(())
+        } else {
+            Err(self.type_error(expected[expected.len() - 1]))
+        }
+    }
+
+    fn integer_value<T: TryFrom<i64>>(&self, expected: VariableType) -> Result<T> {
+        self.value
+            .as_i64()
+            .and_then(|v| T::try_from(v).ok())
+            .ok_or_else(|| self.type_error(expected))
+    }
+
+    fn string_value(&self, expected: VariableType) -> Result<String> {
+        match &self.value {
+            Value::String(v) => Ok(v.to_owned()),
+            Value::Null => Err(self.type_error(expected)),
+            value => Ok(value.to_string()),
+        }
+    }
+
+    fn type_error(&self, expected: VariableType) -> Error {
+        Error::VariableTypeError {
+            expected: Some(expected),
+            actual: format!("{self}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn to_json(variable: &Variable) -> Value {
+        serde_json::to_value(variable).unwrap()
+    }
+
+    fn assert_type_error(result: Result<impl std::fmt::Debug>, expected: Option<VariableType>) {
+        match result {
+            Err(Error::VariableTypeError { expected: e, .. }) => assert_eq!(e, expected),
+            other => panic!("expected a variable type error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn serializes_constructors() {
+        assert_eq!(
+            to_json(&Variable::string("loan")),
+            json!({"type": "String", "value": "loan"})
+        );
+        assert_eq!(
+            to_json(&Variable::boolean(true)),
+            json!({"type": "Boolean", "value": true})
+        );
+        assert_eq!(
+            to_json(&Variable::short(7)),
+            json!({"type": "Short", "value": 7})
+        );
+        assert_eq!(
+            to_json(&Variable::integer(-42)),
+            json!({"type": "Integer", "value": -42})
+        );
+        assert_eq!(
+            to_json(&Variable::long(5_000_000_000)),
+            json!({"type": "Long", "value": 5_000_000_000_i64})
+        );
+        assert_eq!(
+            to_json(&Variable::double(1.5)),
+            json!({"type": "Double", "value": 1.5})
+        );
+        assert_eq!(
+            to_json(&Variable::date("2013-06-30T21:33:00.000+0200")),
+            json!({"type": "Date", "value": "2013-06-30T21:33:00.000+0200"})
+        );
+        assert_eq!(
+            to_json(&Variable::json(r#"{"a":1}"#)),
+            json!({"type": "Json", "value": "{\"a\":1}"})
+        );
+        assert_eq!(
+            to_json(&Variable::xml("<a/>")),
+            json!({"type": "Xml", "value": "<a/>"})
+        );
+        assert_eq!(
+            to_json(&Variable::bytes("AQI=")),
+            json!({"type": "Bytes", "value": "AQI="})
+        );
+        assert_eq!(
+            to_json(&Variable::null()),
+            json!({"type": "Null", "value": null})
+        );
+    }
+
+    #[test]
+    fn serializes_value_info() {
+        assert_eq!(
+            to_json(&Variable::object(
+                "{}",
+                "com.example.Loan",
+                "application/json"
+            )),
+            json!({
+                "type": "Object",
+                "value": "{}",
+                "valueInfo": {
+                    "objectTypeName": "com.example.Loan",
+                    "serializationDataFormat": "application/json"
+                }
+            })
+        );
+        assert_eq!(
+            to_json(&Variable::file("aGk=", "hi.txt", Some("text/plain"))),
+            json!({
+                "type": "File",
+                "value": "aGk=",
+                "valueInfo": {
+                    "objectTypeName": null,
+                    "serializationDataFormat": null,
+                    "filename": "hi.txt",
+                    "mimetype": "text/plain"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn round_trips_typed_values() {
+        let values = [
+            VariableValue::String("loan".into()),
+            VariableValue::Boolean(false),
+            VariableValue::Short(7),
+            VariableValue::Integer(-42),
+            VariableValue::Long(5_000_000_000),
+            VariableValue::Double(1.5),
+            VariableValue::Date("2013-06-30T21:33:00.000+0200".into()),
+            VariableValue::Json(r#"{"a":1}"#.into()),
+            VariableValue::Xml("<a/>".into()),
+            VariableValue::Object {
+                value: Value::String("{}".into()),
+                object_type_name: Some("com.example.Loan".into()),
+                serialization_data_format: Some("application/json".into()),
+            },
+            VariableValue::File {
+                value: Some("aGk=".into()),
+                filename: Some("hi.txt".into()),
+                mime_type: Some("text/plain".into()),
+                encoding: None,
+            },
+            VariableValue::Bytes("AQI=".into()),
+            VariableValue::Null,
+        ];
+
+        for value in values {
+            let variable = Variable::from(value.clone());
+            assert_eq!(VariableValue::try_from(&variable).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn reads_type_case_insensitively() {
+        let variable = Variable::new("string", Value::String("loan".into()));
+
+        assert_eq!(variable.variable_type().unwrap(), VariableType::String);
+        assert_eq!(variable.as_str().unwrap(), "loan");
+    }
+
+    #[test]
+    fn widens_integer_accessors() {
+        assert_eq!(Variable::short(7).as_i32().unwrap(), 7);
+        assert_eq!(Variable::integer(-42).as_i64().unwrap(), -42);
+        assert_eq!(Variable::long(3).as_f64().unwrap(), 3.0);
+    }
+
+    #[test]
+    fn rejects_other_types() {
+        assert_type_error(Variable::long(1).as_i32(), Some(VariableType::Integer));
+        assert_type_error(Variable::boolean(true).as_str(), Some(VariableType::String));
+        assert_type_error(Variable::string("a").as_bool(), Some(VariableType::Boolean));
+        assert_type_error(Variable::xml("<a/>").as_json(), Some(VariableType::Json));
+    }
+
+    #[test]
+    fn rejects_values_out_of_range() {
+        let variable = Variable::new("Short", json!(100_000));
+
+        assert_type_error(variable.as_i16(), Some(VariableType::Short));
+    }
+
+    #[test]
+    fn rejects_unknown_type() {
+        let variable = Variable::new("Money", json!(10));
+
+        assert_type_error(variable.variable_type(), None);
+        assert_type_error(variable.as_i64(), None);
+        assert_type_error(variable.typed_value(), None);
+    }
+}
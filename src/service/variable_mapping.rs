@@ -0,0 +1,878 @@
+use std::collections::hash_map::Iter;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use serde::de::{DeserializeOwned, DeserializeSeed, MapAccess, Visitor};
+use serde::ser::{Impossible, SerializeMap, SerializeStruct};
+use serde::{forward_to_deserialize_any, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::{Error, Result, Task, Variable, VariableType};
+
+/// Maps variables to and from serde structs.
+///
+/// Each field of a struct becomes a variable, with the type inferred from the type of the
+/// field: `i16` maps to `Short`, `i32` to `Integer`, `i64` to `Long`, `f64` to `Double`,
+/// strings to `String`, `bool` to `Boolean` and `None` to `Null`. Sequences, maps and nested
+/// structs are stored as `Json` variables.
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use camunda_external_task_client_rust::*;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct LoanRequest {
+///     amount: i64,
+///     scores: Vec<u8>,
+/// }
+///
+/// # fn main() -> Result<()> {
+/// let variables = Variables::from_serializable(&LoanRequest { amount: 1000, scores: vec![9, 1] })?;
+/// assert_eq!(variables["amount"].kind, "Long");
+/// assert_eq!(variables["scores"].kind, "Json");
+///
+/// let request: LoanRequest = Variables::to_deserializable(&variables)?;
+/// assert_eq!(request.scores, vec![9, 1]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Variables;
+
+impl Variables {
+    /// Converts the fields of a struct or the entries of a map into variables.
+    pub fn from_serializable<T: Serialize + ?Sized>(
+        value: &T,
+    ) -> Result<HashMap<String, Variable>> {
+        value.serialize(FieldsSerializer).map_err(Error::from)
+    }
+
+    /// Converts variables into a struct, using the variable names as field names.
+    pub fn to_deserializable<T: DeserializeOwned>(
+        variables: &HashMap<String, Variable>,
+    ) -> Result<T> {
+        T::deserialize(VariablesDeserializer { variables }).map_err(Error::from)
+    }
+}
+
+impl Task {
+    /// Converts the variables of the task into a struct, see `Variables`.
+    pub fn variables_as<T: DeserializeOwned>(&self) -> Result<T> {
+        Variables::to_deserializable(&self.variables)
+    }
+}
+
+impl Variable {
+    /// Converts a value into a variable, with the type inferred as described in `Variables`.
+    pub fn from_serializable<T: Serialize + ?Sized>(value: &T) -> Result<Self> {
+        to_variable(value).map_err(Error::from)
+    }
+
+    /// Converts a value into a variable of the given type. `Json` variables contain the
+    /// value serialized to JSON. Other types are inferred and must match, except that
+    /// strings can be stored as `Date`, `Xml` or `Bytes` and numbers as any wider type,
+    /// from `Short` over `Integer` and `Long` to `Double`.
+    pub fn from_serializable_as<T: Serialize + ?Sized>(
+        value: &T,
+        variable_type: VariableType,
+    ) -> Result<Self> {
+        if variable_type == VariableType::Json {
+            let json =
+                serde_json::to_string(value).map_err(|e| MappingError::new(e.to_string()))?;
+            return Ok(Variable::json(json));
+        }
+        let variable = Variable::from_serializable(value)?;
+        let actual = variable.variable_type()?;
+        if actual != VariableType::Null && !can_store_as(actual, variable_type) {
+            return Err(Error::VariableParseError {
+                name: None,
+                reason: format!("cannot store {actual} value as {variable_type}"),
+            });
+        }
+        let value = match (variable_type, variable.value.as_f64()) {
+            (VariableType::Double, Some(value)) => value.into(),
+            _ => variable.value,
+        };
+        Ok(Variable {
+            kind: variable_type.as_str().to_string(),
+            value,
+            value_info: variable.value_info,
+        })
+    }
+
+    /// Converts the value of the variable into any deserializable type. `Json` variables
+    /// are parsed from their serialized document first.
+    pub fn deserialize_as<T: DeserializeOwned>(&self) -> Result<T> {
+        let value = json_value(self)?;
+        T::deserialize(value).map_err(|e| MappingError::new(e.to_string()).into())
+    }
+}
+
+#[derive(Debug)]
+struct MappingError {
+    name: Option<String>,
+    reason: String,
+    compound: bool,
+}
+
+impl MappingError {
+    fn new(reason: String) -> Self {
+        Self {
+            name: None,
+            reason,
+            compound: false,
+        }
+    }
+
+    /// Marks a value which has no variable type of its own and is stored as JSON.
+    fn compound() -> Self {
+        Self {
+            name: None,
+            reason: "compound value".to_string(),
+            compound: true,
+        }
+    }
+
+    fn for_variable(mut self, name: &str) -> Self {
+        self.name.get_or_insert_with(|| name.to_string());
+        self
+    }
+}
+
+impl Display for MappingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "variable {name}: {}", self.reason),
+            None => f.write_str(&self.reason),
+        }
+    }
+}
+
+impl std::error::Error for MappingError {}
+
+impl serde::ser::Error for MappingError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::new(msg.to_string())
+    }
+}
+
+impl serde::de::Error for MappingError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::new(msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Self::new("variable is missing".to_string()).for_variable(field)
+    }
+}
+
+impl From<MappingError> for Error {
+    fn from(e: MappingError) -> Self {
+        Error::VariableParseError {
+            name: e.name,
+            reason: e.reason,
+        }
+    }
+}
+
+/// Whether a value of the inferred type `actual` can be stored as a variable of `target`.
+fn can_store_as(actual: VariableType, target: VariableType) -> bool {
+    use VariableType::*;
+    match target {
+        String | Date | Xml | Bytes => actual == String,
+        Boolean => actual == Boolean,
+        Short => actual == Short,
+        Integer => matches!(actual, Short | Integer),
+        Long => matches!(actual, Short | Integer | Long),
+        Double => matches!(actual, Short | Integer | Long | Double),
+        Json => true,
+        Object | File | Null => false,
+    }
+}
+
+fn to_variable<T: Serialize + ?Sized>(value: &T) -> std::result::Result<Variable, MappingError> {
+    match value.serialize(VariableSerializer) {
+        Err(e) if e.compound => serde_json::to_string(value)
+            .map(Variable::json)
+            .map_err(|e| MappingError::new(e.to_string())),
+        result => result,
+    }
+}
+
+/// Returns the value of a variable as JSON, parsing serialized JSON documents.
+fn json_value(variable: &Variable) -> std::result::Result<Value, MappingError> {
+    let is_json = match variable.variable_type() {
+        Ok(VariableType::Json) => true,
+        Ok(VariableType::Object) => variable
+            .value_info
+            .as_ref()
+            .and_then(|value_info| value_info.serialization_data_format.as_deref())
+            .is_some_and(|format| format == "application/json"),
+        _ => false,
+    };
+    match &variable.value {
+        Value::String(json) if is_json => {
+            serde_json::from_str(json).map_err(|e| MappingError::new(e.to_string()))
+        }
+        value => Ok(value.clone()),
+    }
+}
+
+/// Serializes structs and maps into variables, one per field or entry.
+struct FieldsSerializer;
+
+struct FieldsCollector {
+    variables: HashMap<String, Variable>,
+    next_key: Option<String>,
+}
+
+fn expected_struct<T>() -> std::result::Result<T, MappingError> {
+    Err(MappingError::new(
+        "only structs and maps can be converted into variables".to_string(),
+    ))
+}
+
+impl Serializer for FieldsSerializer {
+    type Ok = HashMap<String, Variable>;
+    type Error = MappingError;
+    type SerializeSeq = Impossible<Self::Ok, Self::Error>;
+    type SerializeTuple = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = FieldsCollector;
+    type SerializeStruct = FieldsCollector;
+    type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
+
+    fn serialize_bool(self, _v: bool) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_i8(self, _v: i8) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_i16(self, _v: i16) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_i32(self, _v: i32) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_i64(self, _v: i64) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_u8(self, _v: u8) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_u16(self, _v: u16) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_u32(self, _v: u32) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_u64(self, _v: u64) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_f32(self, _v: f32) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_f64(self, _v: f64) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_char(self, _v: char) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_str(self, _v: &str) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_bytes(self, _v: &[u8]) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_none(self) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_some<T: Serialize + ?Sized>(
+        self,
+        value: &T,
+    ) -> std::result::Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_unit_struct(
+        self,
+        _name: &'static str,
+    ) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(HashMap::new())
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> std::result::Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> std::result::Result<Self::Ok, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_seq(
+        self,
+        _len: Option<usize>,
+    ) -> std::result::Result<Self::SerializeSeq, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_tuple(
+        self,
+        _len: usize,
+    ) -> std::result::Result<Self::SerializeTuple, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> std::result::Result<Self::SerializeTupleStruct, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> std::result::Result<Self::SerializeTupleVariant, Self::Error> {
+        expected_struct()
+    }
+    fn serialize_map(
+        self,
+        _len: Option<usize>,
+    ) -> std::result::Result<Self::SerializeMap, Self::Error> {
+        Ok(FieldsCollector {
+            variables: HashMap::new(),
+            next_key: None,
+        })
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> std::result::Result<Self::SerializeStruct, Self::Error> {
+        self.serialize_map(None)
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> std::result::Result<Self::SerializeStructVariant, Self::Error> {
+        expected_struct()
+    }
+}
+
+impl FieldsCollector {
+    fn insert<T: Serialize + ?Sized>(
+        &mut self,
+        name: String,
+        value: &T,
+    ) -> std::result::Result<(), MappingError> {
+        let variable = to_variable(value).map_err(|e| e.for_variable(&name))?;
+        self.variables.insert(name, variable);
+        Ok(())
+    }
+}
+
+impl SerializeStruct for FieldsCollector {
+    type Ok = HashMap<String, Variable>;
+    type Error = MappingError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> std::result::Result<(), Self::Error> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(self.variables)
+    }
+}
+
+impl SerializeMap for FieldsCollector {
+    type Ok = HashMap<String, Variable>;
+    type Error = MappingError;
+
+    fn serialize_key<T: Serialize + ?Sized>(
+        &mut self,
+        key: &T,
+    ) -> std::result::Result<(), Self::Error> {
+        match serde_json::to_value(key) {
+            Ok(Value::String(key)) => {
+                self.next_key = Some(key);
+                Ok(())
+            }
+            _ => Err(MappingError::new(
+                "variable names must be strings".to_string(),
+            )),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> std::result::Result<(), Self::Error> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| MappingError::new("value without name".to_string()))?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(self.variables)
+    }
+}
+
+/// Serializes a single value into a variable. Fails with a compound error for values which
+/// are stored as JSON.
+struct VariableSerializer;
+
+impl Serializer for VariableSerializer {
+    type Ok = Variable;
+    type Error = MappingError;
+    type SerializeSeq = Impossible<Self::Ok, Self::Error>;
+    type SerializeTuple = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = Impossible<Self::Ok, Self::Error>;
+    type SerializeStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
+
+    fn serialize_bool(self, v: bool) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::boolean(v))
+    }
+    fn serialize_i8(self, v: i8) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::short(v.into()))
+    }
+    fn serialize_i16(self, v: i16) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::short(v))
+    }
+    fn serialize_i32(self, v: i32) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::integer(v))
+    }
+    fn serialize_i64(self, v: i64) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::long(v))
+    }
+    fn serialize_u8(self, v: u8) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::short(v.into()))
+    }
+    fn serialize_u16(self, v: u16) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::integer(v.into()))
+    }
+    fn serialize_u32(self, v: u32) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::long(v.into()))
+    }
+    fn serialize_u64(self, v: u64) -> std::result::Result<Self::Ok, Self::Error> {
+        i64::try_from(v)
+            .map(Variable::long)
+            .map_err(|_| MappingError::new(format!("{v} does not fit into a Long")))
+    }
+    fn serialize_f32(self, v: f32) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::double(v.into()))
+    }
+    fn serialize_f64(self, v: f64) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::double(v))
+    }
+    fn serialize_char(self, v: char) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::string(v))
+    }
+    fn serialize_str(self, v: &str) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::string(v))
+    }
+    fn serialize_bytes(self, _v: &[u8]) -> std::result::Result<Self::Ok, Self::Error> {
+        Err(MappingError::compound())
+    }
+    fn serialize_none(self) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::null())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(
+        self,
+        value: &T,
+    ) -> std::result::Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::null())
+    }
+    fn serialize_unit_struct(
+        self,
+        _name: &'static str,
+    ) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::null())
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> std::result::Result<Self::Ok, Self::Error> {
+        Ok(Variable::string(variant))
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> std::result::Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> std::result::Result<Self::Ok, Self::Error> {
+        Err(MappingError::compound())
+    }
+    fn serialize_seq(
+        self,
+        _len: Option<usize>,
+    ) -> std::result::Result<Self::SerializeSeq, Self::Error> {
+        Err(MappingError::compound())
+    }
+    fn serialize_tuple(
+        self,
+        _len: usize,
+    ) -> std::result::Result<Self::SerializeTuple, Self::Error> {
+        Err(MappingError::compound())
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> std::result::Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(MappingError::compound())
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> std::result::Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(MappingError::compound())
+    }
+    fn serialize_map(
+        self,
+        _len: Option<usize>,
+    ) -> std::result::Result<Self::SerializeMap, Self::Error> {
+        Err(MappingError::compound())
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> std::result::Result<Self::SerializeStruct, Self::Error> {
+        Err(MappingError::compound())
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> std::result::Result<Self::SerializeStructVariant, Self::Error> {
+        Err(MappingError::compound())
+    }
+}
+
+/// Deserializes a map of variables like a JSON object, with one entry per variable.
+struct VariablesDeserializer<'a> {
+    variables: &'a HashMap<String, Variable>,
+}
+
+impl<'de> Deserializer<'de> for VariablesDeserializer<'_> {
+    type Error = MappingError;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        visitor.visit_map(VariablesMapAccess {
+            iter: self.variables.iter(),
+            next_value: None,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct VariablesMapAccess<'a> {
+    iter: Iter<'a, String, Variable>,
+    next_value: Option<(&'a String, &'a Variable)>,
+}
+
+impl<'de> MapAccess<'de> for VariablesMapAccess<'_> {
+    type Error = MappingError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> std::result::Result<Option<K::Value>, Self::Error> {
+        match self.iter.next() {
+            Some((name, variable)) => {
+                self.next_value = Some((name, variable));
+                seed.deserialize(Value::String(name.to_owned()))
+                    .map(Some)
+                    .map_err(|e| MappingError::new(e.to_string()).for_variable(name))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        let (name, variable) = self
+            .next_value
+            .take()
+            .ok_or_else(|| MappingError::new("value without name".to_string()))?;
+        let value = json_value(variable).map_err(|e| e.for_variable(name))?;
+        seed.deserialize(value)
+            .map_err(|e| MappingError::new(e.to_string()).for_variable(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Status {
+        Open,
+        Closed,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Order {
+        id: String,
+        amount: i64,
+        quantity: i32,
+        rating: i16,
+        price: f64,
+        express: bool,
+        status: Status,
+        items: Vec<String>,
+        note: Option<String>,
+    }
+
+    fn order() -> Order {
+        Order {
+            id: "A-1".into(),
+            amount: 1000,
+            quantity: 3,
+            rating: 5,
+            price: 9.5,
+            express: true,
+            status: Status::Closed,
+            items: vec!["book".into(), "pen".into()],
+            note: None,
+        }
+    }
+
+    fn to_variables<T: Serialize>(value: &T) -> HashMap<String, Variable> {
+        match Variables::from_serializable(value) {
+            Ok(variables) => variables,
+            Err(e) => panic!("{e:?}"),
+        }
+    }
+
+    fn parse_error<T: std::fmt::Debug>(result: Result<T>) -> (Option<String>, String) {
+        match result {
+            Err(Error::VariableParseError { name, reason }) => (name, reason),
+            result => panic!("expected a parse error, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn round_trips_struct() {
+        let variables = to_variables(&order());
+
+        assert_eq!(variables["id"].kind, "String");
+        assert_eq!(variables["amount"].kind, "Long");
+        assert_eq!(variables["quantity"].kind, "Integer");
+        assert_eq!(variables["rating"].kind, "Short");
+        assert_eq!(variables["price"].kind, "Double");
+        assert_eq!(variables["express"].kind, "Boolean");
+        assert_eq!(variables["status"].kind, "String");
+        assert_eq!(variables["status"].value, json!("Closed"));
+        assert_eq!(variables["items"].kind, "Json");
+        assert_eq!(variables["items"].value, json!(r#"["book","pen"]"#));
+        assert_eq!(variables["note"].kind, "Null");
+
+        let order: Order = Variables::to_deserializable(&variables).unwrap();
+        assert_eq!(order, self::order());
+    }
+
+    #[test]
+    fn missing_and_null_variables_become_none() {
+        let mut variables = to_variables(&order());
+        variables.remove("note");
+        let order: Order = Variables::to_deserializable(&variables).unwrap();
+        assert_eq!(order.note, None);
+
+        variables.insert("note".into(), Variable::null());
+        let order: Order = Variables::to_deserializable(&variables).unwrap();
+        assert_eq!(order.note, None);
+    }
+
+    #[test]
+    fn missing_variable_is_named() {
+        let mut variables = to_variables(&order());
+        variables.remove("amount");
+
+        let (name, reason) = parse_error(Variables::to_deserializable::<Order>(&variables));
+
+        assert_eq!(name.as_deref(), Some("amount"));
+        assert_eq!(reason, "variable is missing");
+    }
+
+    #[test]
+    fn invalid_variable_is_named() {
+        let mut variables = to_variables(&order());
+        variables.insert("express".into(), Variable::string("yes"));
+
+        let (name, _) = parse_error(Variables::to_deserializable::<Order>(&variables));
+
+        assert_eq!(name.as_deref(), Some("express"));
+    }
+
+    #[test]
+    fn u64_overflow_fails() {
+        let mut values = BTreeMap::new();
+        values.insert("small", 1u64);
+        values.insert("large", u64::MAX);
+
+        let (_, reason) = parse_error(Variables::from_serializable(&values).map(|_| ()));
+
+        assert_eq!(reason, format!("{} does not fit into a Long", u64::MAX));
+    }
+
+    #[test]
+    fn non_string_map_keys_fail() {
+        let mut values = BTreeMap::new();
+        values.insert(1, "one");
+
+        let (_, reason) = parse_error(Variables::from_serializable(&values).map(|_| ()));
+
+        assert_eq!(reason, "variable names must be strings");
+    }
+
+    #[test]
+    fn only_structs_and_maps_convert_into_variables() {
+        assert!(Variables::from_serializable(&42).is_err());
+        assert!(Variables::from_serializable(&vec![1, 2]).is_err());
+    }
+
+    #[test]
+    fn reads_json_objects() {
+        #[derive(Deserialize)]
+        struct Customer {
+            address: BTreeMap<String, String>,
+        }
+        let mut variables = HashMap::new();
+        variables.insert(
+            "address".to_string(),
+            Variable::object(
+                r#"{"city":"Berlin"}"#,
+                "java.util.HashMap",
+                "application/json",
+            ),
+        );
+
+        let customer: Customer = Variables::to_deserializable(&variables).unwrap();
+
+        assert_eq!(customer.address["city"], "Berlin");
+    }
+
+    #[test]
+    fn enums_with_data_are_stored_as_json() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        enum Shipping {
+            Parcel { weight: u32 },
+        }
+        let shipping = Shipping::Parcel { weight: 3 };
+
+        let variable = match Variable::from_serializable(&shipping) {
+            Ok(variable) => variable,
+            Err(e) => panic!("{e:?}"),
+        };
+
+        assert_eq!(variable.kind, "Json");
+        assert_eq!(variable.deserialize_as::<Shipping>().unwrap(), shipping);
+    }
+
+    fn store_as<T: Serialize>(value: &T, variable_type: VariableType) -> Result<Variable> {
+        Variable::from_serializable_as(value, variable_type)
+    }
+
+    #[test]
+    fn stores_values_as_compatible_types() {
+        let date = store_as(&"2024-01-01T00:00:00.000+0000", VariableType::Date).unwrap();
+        assert_eq!(date.kind, "Date");
+        assert_eq!(store_as(&"<a/>", VariableType::Xml).unwrap().kind, "Xml");
+        assert_eq!(
+            store_as(&"AQI=", VariableType::Bytes).unwrap().kind,
+            "Bytes"
+        );
+
+        assert_eq!(
+            store_as(&1i16, VariableType::Integer).unwrap().kind,
+            "Integer"
+        );
+        assert_eq!(store_as(&1i32, VariableType::Long).unwrap().kind, "Long");
+        let double = store_as(&1i64, VariableType::Double).unwrap();
+        assert_eq!(double.kind, "Double");
+        assert_eq!(double.value, json!(1.0));
+
+        let null = store_as(&None::<i32>, VariableType::Date).unwrap();
+        assert_eq!(null.kind, "Date");
+        assert!(null.value.is_null());
+
+        let json = store_as(&vec![1, 2], VariableType::Json).unwrap();
+        assert_eq!(json.value, json!("[1,2]"));
+    }
+
+    #[test]
+    fn rejects_incompatible_types() {
+        let (_, reason) = parse_error(store_as(&1i64, VariableType::Integer).map(|_| ()));
+        assert_eq!(reason, "cannot store Long value as Integer");
+
+        assert!(store_as(&1.5, VariableType::Long).is_err());
+        assert!(store_as(&true, VariableType::String).is_err());
+        assert!(store_as(&1, VariableType::Date).is_err());
+        assert!(store_as(&"a", VariableType::Object).is_err());
+    }
+}